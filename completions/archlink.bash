@@ -0,0 +1,31 @@
+# bash completion for archlink. Package names come from `archlink __complete`,
+# and installed ones, for `remove`, from `pacman -Qq`.
+
+_archlink() {
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    local commands="search discover install compare files why upgrade remove export-manifest doctor log fav config cache self-update stats version help"
+
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "$commands" -- "$cur"))
+        return
+    fi
+    [[ $cur == -* ]] && return
+
+    case ${COMP_WORDS[1]} in
+        install | compare | why)
+            COMPREPLY=($(archlink __complete "$cur" 2>/dev/null))
+            ;;
+        remove)
+            COMPREPLY=($(compgen -W "$(pacman -Qq 2>/dev/null)" -- "$cur"))
+            ;;
+        fav)
+            if [[ $COMP_CWORD -eq 2 ]]; then
+                COMPREPLY=($(compgen -W "add remove list install" -- "$cur"))
+            elif [[ ${COMP_WORDS[2]} == add ]]; then
+                COMPREPLY=($(archlink __complete "$cur" 2>/dev/null))
+            fi
+            ;;
+    esac
+}
+
+complete -F _archlink archlink
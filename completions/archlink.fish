@@ -0,0 +1,15 @@
+# fish completion for archlink. Package names come from `archlink __complete`,
+# and installed ones, for `remove`, from `pacman -Qq`.
+
+set -l commands search discover install compare files why upgrade remove export-manifest doctor log fav config cache self-update stats version
+
+complete -c archlink -f
+complete -c archlink -n "not __fish_seen_subcommand_from $commands" -a "$commands"
+complete -c archlink -n "__fish_seen_subcommand_from install compare why" \
+    -a "(archlink __complete (commandline -ct) 2>/dev/null)"
+complete -c archlink -n "__fish_seen_subcommand_from remove; and not __fish_seen_subcommand_from fav" \
+    -a "(pacman -Qq 2>/dev/null)"
+complete -c archlink -n "__fish_seen_subcommand_from fav; and not __fish_seen_subcommand_from add remove list install" \
+    -a "add remove list install"
+complete -c archlink -n "__fish_seen_subcommand_from fav; and __fish_seen_subcommand_from add" \
+    -a "(archlink __complete (commandline -ct) 2>/dev/null)"
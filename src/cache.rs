@@ -0,0 +1,279 @@
+use crate::{RankedResults, SearchOptions, write_atomic};
+use std::env;
+use std::fs::{self, FileTimes, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+/// Bumped whenever the on-disk format changes so old entries are ignored.
+const CACHE_VERSION: u8 = 10;
+
+/// How long a cached search stays fresh.
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Entry and size caps used when the config sets none.
+pub const DEFAULT_MAX_ENTRIES: usize = 500;
+pub const DEFAULT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Where the cache lives and how large it may grow, set once from the config.
+struct Settings {
+    dir: Option<PathBuf>,
+    max_entries: usize,
+    max_bytes: u64,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Applies the `cache_dir`, `cache_max_entries` and `cache_max_bytes`
+/// config keys. Unset keys keep the XDG directory and default caps.
+pub fn configure(dir: Option<PathBuf>, max_entries: Option<usize>, max_bytes: Option<u64>) {
+    let _ = SETTINGS.set(Settings {
+        dir,
+        max_entries: max_entries.unwrap_or(DEFAULT_MAX_ENTRIES),
+        max_bytes: max_bytes.unwrap_or(DEFAULT_MAX_BYTES),
+    });
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings {
+        dir: None,
+        max_entries: DEFAULT_MAX_ENTRIES,
+        max_bytes: DEFAULT_MAX_BYTES,
+    })
+}
+
+/// Builds the cache key for a search. Every option that changes which
+/// packages come back, or in which order, has to be part of the key.
+pub fn key(query: &str, options: &SearchOptions) -> String {
+    let mut normalized = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !options.case_sensitive {
+        normalized = normalized.to_lowercase();
+    }
+    let input = format!(
+        "{CACHE_VERSION}\0{normalized}\0{}\0{}\0{}\0{:?}\0{}\0{}\0{}\0{:?}\0{}\0{}\0{}\0{}\0{}\0{}\0{}\0{:?}\0{}",
+        options.aur_by.as_deref().unwrap_or(""),
+        options.max_results,
+        options.case_sensitive,
+        options.match_algorithm,
+        options.include_testing,
+        options.include_flatpak,
+        options.only_installed,
+        options.sort,
+        options.min_votes,
+        options.prefer.as_deref().unwrap_or(""),
+        options.prefer_margin,
+        options.merge_arch,
+        options.match_all,
+        options.trusted_maintainers.join(","),
+        options.prefix,
+        options.ranking,
+        options.aur_only
+    );
+    format!("{:016x}", fnv1a(input.as_bytes()))
+}
+
+/// The cache key for `archlink __complete`'s candidates for `partial`, kept
+/// apart from search keys by the prefix.
+pub fn completion_key(partial: &str) -> String {
+    let input = format!("{CACHE_VERSION}\0complete\0{partial}");
+    format!("{:016x}", fnv1a(input.as_bytes()))
+}
+
+/// Loads cached results for `key`. Entries older than the TTL are skipped
+/// unless `allow_stale` is set, which `--offline` uses.
+pub fn load(key: &str, allow_stale: bool) -> Option<RankedResults> {
+    let path = dir()?.join(format!("{key}.json"));
+    if !allow_stale {
+        let age = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+        if age > CACHE_TTL {
+            return None;
+        }
+    }
+
+    let bytes = fs::read(&path).ok()?;
+    let (&version, body) = bytes.split_first()?;
+    if version != CACHE_VERSION {
+        return None;
+    }
+    let ranked = serde_json::from_slice(body).ok()?;
+    mark_used(&path);
+    Some(ranked)
+}
+
+/// Bumps the entry's access time, which eviction goes by. The modification
+/// time is left alone since the TTL is measured from it.
+fn mark_used(path: &Path) {
+    let times = FileTimes::new().set_accessed(SystemTime::now());
+    let _ = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_times(times));
+}
+
+/// Stores results for `key`. Caching is best-effort, so failures are ignored.
+pub fn store(key: &str, ranked: &RankedResults) {
+    let Some(dir) = dir() else {
+        return;
+    };
+    let Ok(body) = serde_json::to_vec(ranked) else {
+        return;
+    };
+
+    let mut bytes = Vec::with_capacity(body.len() + 1);
+    bytes.push(CACHE_VERSION);
+    bytes.extend(body);
+    let path = dir.join(format!("{key}.json"));
+    if fs::create_dir_all(&dir).is_ok() && write_atomic(&path, bytes).is_ok() {
+        evict(&dir);
+    }
+}
+
+/// Removes the least recently used entries until the cache is within both
+/// caps.
+fn evict(dir: &Path) {
+    let settings = settings();
+    let mut entries = entries(dir);
+    entries.sort_by_key(|entry| entry.used);
+    let mut count = entries.len();
+    let mut bytes: u64 = entries.iter().map(|entry| entry.size).sum();
+    for entry in entries {
+        if count <= settings.max_entries && bytes <= settings.max_bytes {
+            break;
+        }
+        if fs::remove_file(&entry.path).is_ok() {
+            count -= 1;
+            bytes -= entry.size;
+        }
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    used: SystemTime,
+}
+
+/// The cached searches in `dir`. AUR clones live in a subdirectory and are
+/// not entries.
+fn entries(dir: &Path) -> Vec<Entry> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok().filter(|meta| meta.is_file())?;
+            Some(Entry {
+                path: entry.path(),
+                size: meta.len(),
+                used: meta.accessed().or_else(|_| meta.modified()).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// What `archlink cache stats` reports.
+pub struct Stats {
+    pub dir: PathBuf,
+    pub entries: usize,
+    pub bytes: u64,
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+pub fn stats() -> Option<Stats> {
+    let dir = dir()?;
+    let entries = entries(&dir);
+    let settings = settings();
+    Some(Stats {
+        entries: entries.len(),
+        bytes: entries.iter().map(|entry| entry.size).sum(),
+        dir,
+        max_entries: settings.max_entries,
+        max_bytes: settings.max_bytes,
+    })
+}
+
+/// Creates the cache directory if needed and checks that files can be
+/// written to it.
+pub fn check_writable() -> io::Result<PathBuf> {
+    let dir = dir().ok_or(io::ErrorKind::NotFound)?;
+    fs::create_dir_all(&dir)?;
+    let probe = dir.join(".write-test");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)?;
+    Ok(dir)
+}
+
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = &settings().dir {
+        return Some(dir.clone());
+    }
+    if let Some(cache_home) = env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(cache_home).join("archlink"));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("archlink"))
+}
+
+/// FNV-1a, used instead of `DefaultHasher` so keys stay stable across builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_covers_result_affecting_options() {
+        let base = SearchOptions::default();
+        let base_key = key("vim", &base);
+        let variants = [
+            SearchOptions {
+                min_votes: 10,
+                ..Default::default()
+            },
+            SearchOptions {
+                aur_only: true,
+                ..Default::default()
+            },
+            SearchOptions {
+                include_testing: true,
+                ..Default::default()
+            },
+            SearchOptions {
+                case_sensitive: true,
+                ..Default::default()
+            },
+        ];
+        for options in &variants {
+            assert_ne!(key("vim", options), base_key);
+        }
+    }
+
+    #[test]
+    fn key_ignores_query_case_unless_case_sensitive() {
+        let options = SearchOptions::default();
+        assert_eq!(key("Vim", &options), key("vim", &options));
+        assert_eq!(
+            key("  vim   plugin ", &options),
+            key("vim plugin", &options)
+        );
+
+        let sensitive = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_ne!(key("Vim", &sensitive), key("vim", &sensitive));
+    }
+}
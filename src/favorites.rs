@@ -0,0 +1,61 @@
+//! The user's favorite packages, for `archlink fav`. One name per line in
+//! `$XDG_STATE_HOME/archlink/favorites`, in the order they were added.
+
+use crate::{state_dir, write_atomic};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The favorites, in the order they were added. A missing list reads as
+/// empty.
+pub fn read() -> io::Result<Vec<String>> {
+    let path = path().ok_or(io::ErrorKind::NotFound)?;
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds `package` to the end of the list. Returns false, leaving the list
+/// alone, if it was already there.
+pub fn add(package: &str) -> io::Result<bool> {
+    let mut favorites = read()?;
+    if favorites.iter().any(|favorite| favorite == package) {
+        return Ok(false);
+    }
+    favorites.push(package.to_string());
+    write(&favorites)?;
+    Ok(true)
+}
+
+/// Removes `package` from the list. Returns false if it wasn't there.
+pub fn remove(package: &str) -> io::Result<bool> {
+    let mut favorites = read()?;
+    let before = favorites.len();
+    favorites.retain(|favorite| favorite != package);
+    if favorites.len() == before {
+        return Ok(false);
+    }
+    write(&favorites)?;
+    Ok(true)
+}
+
+fn write(favorites: &[String]) -> io::Result<()> {
+    let path = path().ok_or(io::ErrorKind::NotFound)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut contents = favorites.join("\n");
+    contents.push('\n');
+    write_atomic(&path, contents)
+}
+
+fn path() -> Option<PathBuf> {
+    Some(state_dir()?.join("favorites"))
+}
@@ -0,0 +1,49 @@
+//! archlink's own record of what it installed, kept apart from pacman.log.
+//! Each line is `<UTC timestamp> <package> <version> <source>`.
+
+use crate::{dates, state_dir};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Once the log grows past this, it is moved to `installs.log.1`, replacing
+/// any older rotation, and a fresh log is started.
+const LOG_SIZE_CAP: u64 = 1024 * 1024;
+
+/// Appends an install to the log. Logging is best-effort, so failures are
+/// ignored rather than failing an install that already succeeded.
+pub fn record(package: &str, version: &str, source: &str) {
+    let _ = try_record(package, version, source);
+}
+
+fn try_record(package: &str, version: &str, source: &str) -> io::Result<()> {
+    let path = path().ok_or(io::ErrorKind::NotFound)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() >= LOG_SIZE_CAP) {
+        fs::rename(&path, path.with_extension("log.1"))?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(
+        file,
+        "{} {package} {version} {source}",
+        dates::format_timestamp(dates::now())
+    )
+}
+
+/// The logged installs, oldest first. A missing log reads as empty.
+pub fn read() -> io::Result<String> {
+    let Some(path) = path() else {
+        return Ok(String::new());
+    };
+    match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        other => other,
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    Some(state_dir()?.join("installs.log"))
+}
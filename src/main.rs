@@ -1,15 +1,99 @@
-use clap::{Arg, Command};
-use colored::Colorize;
+mod cache;
+mod dates;
+mod favorites;
+mod install_log;
+mod messages;
+#[cfg(feature = "tui")]
+mod picker;
+mod sources;
+mod table;
+#[cfg(test)]
+mod tests;
+
+use clap::{Arg, ArgAction, Command};
+use colored::{Color, ColoredString, Colorize};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::process::Command as SysCommand;
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::sync::atomic::{self, AtomicBool, AtomicU8, AtomicUsize};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use strsim::levenshtein;
+use tokio::sync::{AcquireError, Semaphore, SemaphorePermit};
 
 const VERSION: &str = "0.1.1";
+const DEFAULT_MAX_RESULTS: usize = 10;
+const PRECHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a passed precheck is trusted, unless the config says otherwise.
+const DEFAULT_PRECHECK_TTL: Duration = Duration::from_secs(30);
+/// How long a request may take, unless `--timeout` says otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Terminals narrower than this get the compact layout, unless the config
+/// says otherwise.
+const DEFAULT_COMPACT_WIDTH: usize = 80;
+const DEFAULT_MAX_CONCURRENCY: usize = 4;
+const DEFAULT_PREFER_MARGIN: u32 = 5;
+/// `discover` hides AUR packages with fewer votes, unless `--min-votes`
+/// says otherwise.
+const DISCOVER_MIN_VOTES: u64 = 10;
+/// Points added to AUR packages from a `trusted_maintainers` entry.
+const TRUSTED_BOOST: u32 = 5;
+const DEFAULT_OFFICIAL_BASE_URL: &str = "https://archlinux.org";
+const DEFAULT_AUR_BASE_URL: &str = "https://aur.archlinux.org";
+/// Shell completion has to feel instant, so it asks the AUR for at most this
+/// long and lists at most `COMPLETE_LIMIT` names.
+const COMPLETE_TIMEOUT: Duration = Duration::from_millis(800);
+const COMPLETE_LIMIT: usize = 50;
+/// Where pacman keeps downloaded packages, which pinned installs look in.
+const PACMAN_CACHE_DIR: &str = "/var/cache/pacman/pkg";
+const CONFIG_PATH: &str = "/etc/archlink/config.toml";
+
+/// Set by the global `--yes` flag; makes every confirmation prompt answer yes.
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Set by `-v`/`-vv`. 1 logs each request, 2 also logs response bodies.
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// Set once `pacman -Fy` has run, so a run never syncs the files database twice.
+static FILES_DB_REFRESHED: AtomicBool = AtomicBool::new(false);
+
+/// Caps how many requests are in flight at once, sized by `max_concurrency`.
+static HTTP_PERMITS: OnceLock<Semaphore> = OnceLock::new();
+
+/// `official_base_url` and `aur_base_url` from the config, without a
+/// trailing slash. Unset means the default archlinux.org hosts.
+static OFFICIAL_BASE_URL: OnceLock<String> = OnceLock::new();
+static AUR_BASE_URL: OnceLock<String> = OnceLock::new();
+
+/// `--timeout`. A rate-limited request only waits out a `Retry-After` that
+/// ends before this much time has passed since its first attempt.
+static REQUEST_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// The hidden `--test-fixtures` directory. When set, `get_json` reads
+/// canned responses from it instead of the network.
+static FIXTURE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// `install_order` from the config. Unset means `InstallTool::DEFAULT_ORDER`.
+static INSTALL_ORDER: OnceLock<Vec<InstallTool>> = OnceLock::new();
+
+/// `escalation` from the config. Unset means `sudo`.
+static ESCALATION: OnceLock<Escalation> = OnceLock::new();
+
+/// How much of a response body `-vv` prints.
+const HTTP_LOG_BODY_LIMIT: usize = 2048;
+
+/// A source that takes longer than this to answer gets a hint to use a
+/// mirror or a longer timeout, however long `--timeout` allows.
+const SLOW_SOURCE_THRESHOLD: Duration = Duration::from_secs(5);
+
 
 #[derive(Serialize, Deserialize, Debug)]
 struct AurPackage {
@@ -19,378 +103,4574 @@ struct AurPackage {
     description: Option<String>,
     #[serde(rename = "Version")]
     version: String,
+    #[serde(rename = "NumVotes", default)]
+    num_votes: u64,
+    #[serde(rename = "LastModified", default)]
+    last_modified: Option<i64>,
+    /// `None` for orphaned packages.
+    #[serde(rename = "Maintainer", default)]
+    maintainer: Option<String>,
+    /// When the package was flagged out of date; `None` if it isn't.
+    #[serde(rename = "OutOfDate", default)]
+    out_of_date: Option<i64>,
+    /// Only present in `type=info` responses.
+    #[serde(rename = "Depends", default)]
+    depends: Vec<String>,
+    /// Only present in `type=info` responses.
+    #[serde(rename = "OptDepends", default)]
+    opt_depends: Vec<String>,
+    /// The PKGBUILD's `arch` list. Only present in `type=info` responses.
+    #[serde(rename = "Arch", default)]
+    arch: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct AurResponse {
-    #[serde(rename = "results")]
+    /// How many results the query matched; 0 is a successful empty search.
+    #[serde(rename = "resultcount", default)]
+    result_count: usize,
+    #[serde(rename = "results", default)]
     results: Vec<AurPackage>,
+    /// Set, with no results, when the RPC rejects the request.
+    #[serde(rename = "error", default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The archlinux.org package search JSON.
+#[derive(Serialize, Deserialize, Debug)]
+struct OfficialResponse {
+    /// Kept raw so each entry is parsed as an `OfficialPackage` on its own,
+    /// and one that doesn't fit can be skipped without failing the rest.
+    #[serde(default)]
+    results: Vec<serde_json::Value>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug)]
+struct OfficialPackage {
+    pkgname: String,
+    #[serde(default)]
+    pkgver: String,
+    #[serde(default)]
+    pkgrel: String,
+    #[serde(default)]
+    pkgdesc: Option<String>,
+    #[serde(default)]
+    repo: Option<String>,
+    #[serde(default)]
+    arch: Option<String>,
+    /// RFC 3339 timestamp.
+    #[serde(default)]
+    last_update: Option<String>,
+    #[serde(default)]
+    installed_size: Option<u64>,
+    /// Only present when the search includes reverse dependencies.
+    #[serde(default)]
+    required_by: Option<Vec<String>>,
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    depends: Vec<String>,
+    /// pacman's `name: reason` form.
+    #[serde(default)]
+    optdepends: Vec<String>,
+}
+
+impl OfficialPackage {
+    /// `pkgver-pkgrel`, as pacman shows it.
+    fn version(&self) -> String {
+        format!("{}-{}", self.pkgver, self.pkgrel)
+    }
+}
+
+/// Where a package was found: `"official"`, `"aur"` or `"flatpak"`.
+type Source = &'static str;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 struct Package {
     name: String,
     version: String,
     description: String,
-    source: &'static str,
+    #[serde(deserialize_with = "deserialize_source")]
+    source: Source,
+    /// Official repository (`core`, `extra`, ...); `None` for AUR packages.
+    repo: Option<String>,
+    arch: Option<String>,
+    /// When the package was last updated, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_updated: Option<i64>,
+    /// Installed size in bytes; only official packages report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    installed_size: Option<u64>,
+    /// How many packages depend on this one, from the official JSON's
+    /// `required_by` when it is included. A rough popularity signal for
+    /// packages without AUR votes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reverse_deps: Option<usize>,
+    /// AUR votes; `None` for other sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    votes: Option<u64>,
+    /// Every arch the package is listed for, once `--merge-arch` has merged
+    /// its rows. `arch` keeps the first one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    arches: Vec<String>,
+    /// Package groups, e.g. `gnome`; only official packages have them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<String>,
+    /// The AUR maintainer; `None` for other sources and orphans.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    maintainer: Option<String>,
+    /// When the AUR package was flagged out of date, in seconds since the
+    /// Unix epoch; `None` if it isn't, and for other sources.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    out_of_date: Option<i64>,
+    /// Maintained by someone in `trusted_maintainers`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    trusted: bool,
+}
+
+/// Maps a source name back onto the static strings used by `Package.source`.
+fn deserialize_source<'de, D>(deserializer: D) -> Result<Source, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let source = String::deserialize(deserializer)?;
+    match source.as_str() {
+        "official" => Ok("official"),
+        "aur" => Ok("aur"),
+        "flatpak" => Ok("flatpak"),
+        other => Err(serde::de::Error::unknown_variant(
+            other,
+            &["official", "aur", "flatpak"],
+        )),
+    }
+}
+
+/// Bumped whenever the shape of the JSON search output changes.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// How search results are written to stdout.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    JsonPretty,
+    Csv,
+}
+
+/// `archlink version --json`, for scripts that check what this build can do.
+#[derive(Serialize)]
+struct VersionInfo {
+    name: &'static str,
+    version: &'static str,
+    /// The compiler that built this binary, as `rustc --version` put it.
+    rustc: &'static str,
+    /// Optional features compiled in.
+    features: Vec<&'static str>,
+    /// The sources a search queries with the current config.
+    sources: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: u32,
+    query: &'a str,
+    results: &'a [Package],
+}
+
+/// The ranked, truncated results of a search, plus how many candidates
+/// each source returned before truncation.
+#[derive(Serialize, Deserialize, Default)]
+struct RankedResults {
+    packages: Vec<Package>,
+    official_total: usize,
+    aur_total: usize,
+    #[serde(default)]
+    flatpak_total: usize,
+}
+
+/// A column of the search results table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Field {
+    Name,
+    Version,
+    Description,
+    Source,
+    Updated,
+    Size,
+    ReverseDeps,
+    Votes,
+    Maintainer,
+    OutOfDate,
+}
+
+impl Field {
+    const DEFAULT: [Field; 4] = [Field::Name, Field::Version, Field::Description, Field::Source];
+
+    /// The columns shown without `--fields`: the defaults, plus the date,
+    /// size or reverse dependency count after the version when sorting by it.
+    /// Sorting by votes, as `discover` does, shows the AUR details instead of
+    /// the source, which is always the AUR.
+    fn defaults(sort: SortBy) -> &'static [Field] {
+        match sort {
+            SortBy::Relevance | SortBy::Name => &Field::DEFAULT,
+            SortBy::Newest => &[
+                Field::Name,
+                Field::Version,
+                Field::Updated,
+                Field::Description,
+                Field::Source,
+            ],
+            SortBy::Smallest | SortBy::Largest => &[
+                Field::Name,
+                Field::Version,
+                Field::Size,
+                Field::Description,
+                Field::Source,
+            ],
+            SortBy::ReverseDeps => &[
+                Field::Name,
+                Field::Version,
+                Field::ReverseDeps,
+                Field::Description,
+                Field::Source,
+            ],
+            SortBy::Votes => &[
+                Field::Name,
+                Field::Version,
+                Field::Votes,
+                Field::Maintainer,
+                Field::OutOfDate,
+                Field::Description,
+            ],
+        }
+    }
+
+    fn value(self, pkg: &Package) -> String {
+        match self {
+            Field::Name => display_name(pkg),
+            Field::Version => pkg.version.clone(),
+            Field::Description => pkg.description.clone(),
+            Field::Source => source_label(pkg),
+            Field::Updated => pkg.last_updated.map(dates::format_date).unwrap_or_default(),
+            Field::Size => pkg.installed_size.map(format_size).unwrap_or_default(),
+            Field::ReverseDeps => pkg.reverse_deps.map(|n| n.to_string()).unwrap_or_default(),
+            Field::Votes => pkg.votes.map(|n| n.to_string()).unwrap_or_default(),
+            Field::Maintainer => match (&pkg.maintainer, pkg.source) {
+                (Some(maintainer), _) => maintainer.clone(),
+                (None, "aur") => messages::orphan(),
+                (None, _) => String::new(),
+            },
+            Field::OutOfDate => pkg.out_of_date.map(dates::format_date).unwrap_or_default(),
+        }
+    }
+
+    fn paint(self, value: &str) -> ColoredString {
+        match self {
+            Field::Name => value.green(),
+            Field::Version => value.blue(),
+            Field::Description => value.normal(),
+            Field::Source => value.cyan(),
+            Field::Updated => value.magenta(),
+            Field::Size => value.yellow(),
+            Field::ReverseDeps => value.yellow(),
+            Field::Votes => value.yellow(),
+            Field::Maintainer => value.magenta(),
+            Field::OutOfDate => value.red(),
+        }
+    }
+}
+
+/// Parses `--fields`: a comma-separated list of known column names.
+fn parse_fields(value: &str) -> Result<Vec<Field>, String> {
+    value
+        .split(',')
+        .map(|field| match field.trim() {
+            "name" => Ok(Field::Name),
+            "version" => Ok(Field::Version),
+            "description" => Ok(Field::Description),
+            "source" => Ok(Field::Source),
+            "updated" => Ok(Field::Updated),
+            "size" => Ok(Field::Size),
+            "rdeps" => Ok(Field::ReverseDeps),
+            "votes" => Ok(Field::Votes),
+            "maintainer" => Ok(Field::Maintainer),
+            "outofdate" => Ok(Field::OutOfDate),
+            other => Err(messages::unknown_field(other)),
+        })
+        .collect()
+}
+
+/// The order search results are listed in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum SortBy {
+    #[default]
+    Relevance,
+    /// Most recently updated first.
+    Newest,
+    /// Smallest installed size first.
+    Smallest,
+    /// Largest installed size first.
+    Largest,
+    /// Most reverse dependencies first.
+    ReverseDeps,
+    /// Alphabetically by name, for `--prefix`.
+    Name,
+    /// Most AUR votes first, for `discover`.
+    Votes,
+}
+
+/// How a package name is compared with the query when ranking.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+enum MatchAlgorithm {
+    /// Edit distance between the whole name and the query.
+    #[default]
+    Levenshtein,
+    /// The query's characters in order, with gaps allowed, like fzf.
+    Fuzzy,
+}
+
+/// Options that shape a single search run.
+#[cfg_attr(test, derive(Default))]
+struct SearchOptions {
+    max_results: usize,
+    /// AUR RPC `by` field. `None` keeps the RPC default of name and description.
+    aur_by: Option<String>,
+    /// Source whose packages win near-ties, i.e. when they score at most
+    /// `prefer_margin` points below a package from another source.
+    prefer: Option<String>,
+    prefer_margin: u32,
+    /// AUR maintainers whose packages get a badge and `TRUSTED_BOOST`.
+    trusted_maintainers: Vec<String>,
+    /// Skip reading and writing the result cache.
+    no_cache: bool,
+    /// Print the selected package's URL instead of installing it.
+    print_url: bool,
+    /// Compare names case-sensitively when ranking.
+    case_sensitive: bool,
+    match_algorithm: MatchAlgorithm,
+    /// Keep official results from the testing and staging repos.
+    include_testing: bool,
+    /// Also search Flatpak remotes.
+    include_flatpak: bool,
+    output: OutputFormat,
+    /// Emit JSON results as a bare array instead of the versioned report.
+    json_array: bool,
+    /// Write the full scoring breakdown here before truncating.
+    rank_debug_export: Option<PathBuf>,
+    /// Offer to install optional dependencies after installing.
+    install_optdepends: bool,
+    /// Only use cached results, however old, and never touch the network.
+    offline: bool,
+    /// Check that archlinux.org is reachable before searching.
+    precheck: bool,
+    /// Skip the precheck if one passed this recently.
+    precheck_ttl: Duration,
+    /// Don't warn when a source fails; the results just leave it out.
+    quiet_errors: bool,
+    /// Exit non-zero after showing the results if any source failed.
+    fail_fast: bool,
+    /// Skip the "Searching ..." line; JSON output never has it.
+    no_banner: bool,
+    /// Check that custom base URLs respond before searching.
+    repo_url_check: bool,
+    /// The per-request `--timeout`, shown by the progress spinner.
+    timeout: Duration,
+    /// Columns to show, in order. `None` keeps the default layout.
+    fields: Option<Vec<Field>>,
+    /// Leave descriptions out of the default layouts, for `--no-description`.
+    no_description: bool,
+    /// Draw the results as a bordered table. Ignored without the `table`
+    /// feature.
+    #[cfg_attr(not(feature = "table"), allow(dead_code))]
+    table: bool,
+    /// Put each result's details on indented lines under its name.
+    compact: bool,
+    /// Show a random sample of the matches, shuffled with this seed, instead
+    /// of the top-ranked ones.
+    random_seed: Option<u64>,
+    /// Keep only packages that are installed locally.
+    only_installed: bool,
+    /// List official packages published for several arches once.
+    merge_arch: bool,
+    /// List official results under their package group.
+    group_by_category: bool,
+    sort: SortBy,
+    /// Drop AUR packages with fewer votes than this.
+    min_votes: u64,
+    /// Keep only packages whose name or description contains every query
+    /// word, for `--and`.
+    match_all: bool,
+    /// Search only the AUR, for `discover`.
+    aur_only: bool,
+    /// Keep only packages whose name starts with the query, ignoring case,
+    /// for `--prefix`.
+    prefix: bool,
+    /// How `score_breakdown` weighs each part of the score.
+    ranking: RankingWeights,
+    /// Color of the selection numbers. `None` hides them, and the prompt.
+    index_color: Option<Color>,
+    /// Replaces the selection prompt; `{count}` becomes the number of results.
+    prompt: Option<String>,
+    /// Pick with the arrow-key selector instead of typing a number. Ignored
+    /// without the `tui` feature.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    interactive: bool,
+}
+
+/// A tool `install_package` can install with. Unknown names in
+/// `install_order` are warned about and dropped by
+/// `deserialize_install_order`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum InstallTool {
+    Pacman,
+    Yay,
+    Paru,
+}
+
+impl InstallTool {
+    const DEFAULT_ORDER: [InstallTool; 3] =
+        [InstallTool::Pacman, InstallTool::Yay, InstallTool::Paru];
+
+    fn name(self) -> &'static str {
+        match self {
+            InstallTool::Pacman => "pacman",
+            InstallTool::Yay => "yay",
+            InstallTool::Paru => "paru",
+        }
+    }
+}
+
+/// Reads `install_order`, dropping names that aren't an `InstallTool` with
+/// a warning, so a typo leaves the rest of the config in effect.
+fn deserialize_install_order<'de, D>(deserializer: D) -> Result<Option<Vec<InstallTool>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let Some(names) = Option::<Vec<String>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let mut order = Vec::new();
+    for name in names {
+        match InstallTool::DEFAULT_ORDER.into_iter().find(|tool| tool.name() == name) {
+            Some(tool) => order.push(tool),
+            None => eprintln!("{}", messages::unknown_install_tool(&name).yellow()),
+        }
+    }
+    Ok(Some(order))
+}
+
+fn install_order() -> &'static [InstallTool] {
+    INSTALL_ORDER.get().map_or(&InstallTool::DEFAULT_ORDER, Vec::as_slice)
+}
+
+/// How pacman gets root, from `escalation` in the config. AUR helpers
+/// escalate on their own and aren't affected.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+enum Escalation {
+    #[default]
+    Sudo,
+    Pkexec,
+    Doas,
+    /// Run pacman directly, e.g. when archlink itself runs as root.
+    None,
+}
+
+impl Escalation {
+    /// The command pacman is run through; `None` for `none`.
+    fn tool(self) -> Option<&'static str> {
+        match self {
+            Escalation::Sudo => Some("sudo"),
+            Escalation::Pkexec => Some("pkexec"),
+            Escalation::Doas => Some("doas"),
+            Escalation::None => None,
+        }
+    }
+}
+
+fn escalation() -> Escalation {
+    ESCALATION.get().copied().unwrap_or_default()
+}
+
+/// A `pacman` command that runs as root through the configured escalation
+/// tool. Fails if that tool isn't installed, rather than trying another.
+fn pacman_as_root() -> Result<SysCommand, String> {
+    match escalation().tool() {
+        Some(tool) if !is_command_in_path(tool) => {
+            Err(format!("{}", messages::escalation_missing(tool).red()))
+        }
+        Some(tool) => {
+            let mut command = SysCommand::new(tool);
+            command.arg("pacman");
+            Ok(command)
+        }
+        None => Ok(SysCommand::new("pacman")),
+    }
+}
+
+/// How a root pacman command is shown, e.g. `sudo pacman -Syu`.
+fn root_pacman(args: &str) -> String {
+    match escalation().tool() {
+        Some(tool) => format!("{tool} pacman {args}"),
+        None => format!("pacman {args}"),
+    }
+}
+
+/// pacman flags passed through to every tool `install_package` tries.
+#[derive(Default)]
+struct InstallOptions {
+    as_deps: bool,
+    as_explicit: bool,
+    /// Install even if the package is already installed.
+    reinstall: bool,
+    /// Files matching this glob may be overwritten, for `--overwrite`.
+    overwrite: Option<String>,
+    /// Install packages built for another architecture, for `--ignore-arch`.
+    ignore_arch: bool,
+}
+
+impl InstallOptions {
+    fn extra_args(&self) -> Vec<&str> {
+        let mut args = Vec::new();
+        // Also keeps the tools from rebuilding or reinstalling anything that
+        // is already up to date, e.g. when an AUR helper resolves a batch.
+        if !self.reinstall {
+            args.push("--needed");
+        }
+        if self.as_deps {
+            args.push("--asdeps");
+        }
+        if self.as_explicit {
+            args.push("--asexplicit");
+        }
+        if let Some(glob) = &self.overwrite {
+            args.extend(["--overwrite", glob.as_str()]);
+        }
+        args
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Config {
     max_results: Option<usize>,
+    /// Set by `load` when `ARCHLINK_MAX_RESULTS` overrode `max_results`.
+    #[serde(skip)]
+    max_results_from_env: bool,
+    /// Packages that `upgrade` always holds back, on top of `--ignore`.
+    ignore_pkgs: Option<Vec<String>>,
+    /// Search and install Flatpak apps too, if `flatpak` is installed.
+    enable_flatpak: Option<bool>,
+    /// Most requests archlink sends at once.
+    max_concurrency: Option<usize>,
+    match_algorithm: Option<MatchAlgorithm>,
+    /// Proxy for every request, overriding `HTTP_PROXY`/`HTTPS_PROXY`.
+    proxy: Option<String>,
+    /// Comma-separated hosts that skip `proxy`. Defaults to `NO_PROXY`.
+    no_proxy: Option<String>,
+    /// Where `install --clone-only` clones AUR repos.
+    aur_clone_dir: Option<PathBuf>,
+    /// Custom selection prompt, with `{count}` for the number of results.
+    prompt: Option<String>,
+    /// Color name for the selection numbers, e.g. `cyan` or `bright blue`.
+    number_color: Option<String>,
+    /// Set to false to list results without numbers or a selection prompt.
+    show_index: Option<bool>,
+    /// Always use the arrow-key selector, as if `--interactive` was passed.
+    interactive: Option<bool>,
+    /// Replaces the default `archlink/<version>` User-Agent header.
+    user_agent: Option<String>,
+    /// Where search results are cached, instead of `$XDG_CACHE_HOME/archlink`.
+    cache_dir: Option<PathBuf>,
+    /// Most cached searches kept; the least recently used go first.
+    cache_max_entries: Option<usize>,
+    /// Most bytes of cached searches kept.
+    cache_max_bytes: Option<u64>,
+    /// How far below another source's package a `--prefer`red one may score
+    /// and still rank above it.
+    prefer_margin: Option<u32>,
+    /// Mirror of the archlinux.org package search API, e.g. for testing.
+    official_base_url: Option<String>,
+    /// Mirror of the AUR RPC.
+    aur_base_url: Option<String>,
+    /// Allow plain HTTP base URLs and redirects from HTTPS to HTTP.
+    allow_insecure: Option<bool>,
+    /// The tools `install` tries, in order, e.g. `["paru", "pacman"]`.
+    #[serde(default, deserialize_with = "deserialize_install_order")]
+    install_order: Option<Vec<InstallTool>>,
+    /// How pacman gets root: `sudo`, `pkexec`, `doas` or `none`.
+    escalation: Option<Escalation>,
+    /// Seconds a passed connectivity precheck is trusted; 0 always checks.
+    precheck_ttl: Option<u64>,
+    /// AUR maintainers whose packages are badged and ranked a little higher.
+    trusted_maintainers: Option<Vec<String>>,
+    /// Terminals narrower than this many columns get the compact layout.
+    compact_width: Option<usize>,
+    /// The `[ranking]` table's scoring weights.
+    ranking: Option<RankingWeights>,
 }
 
 impl Config {
+    /// Loads the config file, then applies `ARCHLINK_MAX_RESULTS`. An
+    /// invalid value is reported here, once, and ignored.
     fn load() -> Self {
-        let config_path = Path::new("/etc/archlink/config.toml");
+        let mut config = Config::load_file();
+        if let Ok(value) = env::var("ARCHLINK_MAX_RESULTS") {
+            match value.trim().parse::<usize>() {
+                Ok(max_results) => {
+                    config.max_results = Some(max_results);
+                    config.max_results_from_env = true;
+                }
+                Err(_) => eprintln!("{}", messages::invalid_max_results_env(&value).yellow()),
+            }
+        }
+        config
+    }
+
+    fn load_file() -> Self {
+        let config_path = Path::new(CONFIG_PATH);
         if config_path.exists() {
             match fs::read_to_string(config_path) {
                 Ok(contents) => match toml::from_str(&contents) {
                     Ok(config) => return config,
                     Err(e) => {
-                        eprintln!(
-                            "{}",
-                            format!("Warning: Invalid config file format: {e}").yellow()
-                        );
+                        eprintln!("{}", messages::invalid_config(e).yellow());
                     }
                 },
                 Err(e) => {
-                    eprintln!(
-                        "{}",
-                        format!("Warning: Failed to read config file: {e}").yellow()
-                    );
+                    eprintln!("{}", messages::config_read_failed(e).yellow());
                 }
             }
         }
         Config {
-            max_results: Some(10),
+            max_results: Some(DEFAULT_MAX_RESULTS),
+            max_results_from_env: false,
+            ignore_pkgs: None,
+            enable_flatpak: None,
+            max_concurrency: Some(DEFAULT_MAX_CONCURRENCY),
+            match_algorithm: None,
+            proxy: None,
+            no_proxy: None,
+            aur_clone_dir: None,
+            prompt: None,
+            number_color: None,
+            show_index: None,
+            interactive: None,
+            user_agent: None,
+            cache_dir: None,
+            cache_max_entries: None,
+            cache_max_bytes: None,
+            prefer_margin: None,
+            official_base_url: None,
+            aur_base_url: None,
+            allow_insecure: None,
+            install_order: None,
+            escalation: None,
+            precheck_ttl: None,
+            trusted_maintainers: None,
+            compact_width: None,
+            ranking: None,
         }
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::load();
-    let max_results = config.max_results.unwrap_or(10);
-
-    let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+    // `discover` is `search` with presets, so the two share every flag.
+    let search = Command::new("search")
+        .about("Search for packages in official repos and AUR")
+        .arg(
+            Arg::new("query")
+                .help("Package name or keyword to search for")
+                .required(true),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .value_name("N")
+                .help("Maximum number of results to show; 0 shows all")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("by")
+                .long("by")
+                .value_name("FIELD")
+                .help("AUR field to search by (maintainer skips the official repos)")
+                .value_parser(["name", "maintainer", "depends"]),
+        )
+        .arg(
+            Arg::new("prefer")
+                .long("prefer")
+                .value_name("SOURCE")
+                .help("Rank this source first when scores are nearly equal")
+                .value_parser(["official", "aur"]),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Ignore cached results and always query the servers")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("min-votes")
+                .long("min-votes")
+                .value_name("N")
+                .help("Hide AUR packages with fewer than N votes")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("and")
+                .long("and")
+                .help("Only show packages matching every word of the query")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("or"),
+        )
+        .arg(
+            Arg::new("or")
+                .long("or")
+                .help("Show packages matching any word of the query (the default)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .help("Only show packages whose name starts with the query, sorted by name")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "newest",
+                    "sort-by-size",
+                    "sort-by-size-desc",
+                    "reverse-deps-count",
+                ]),
+        )
+        .arg(
+            Arg::new("newest")
+                .long("newest")
+                .help("Sort results by last update, newest first")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["sort-by-size", "sort-by-size-desc"]),
+        )
+        .arg(
+            Arg::new("sort-by-size")
+                .long("sort-by-size")
+                .help("Sort results by installed size, smallest first")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("sort-by-size-desc"),
+        )
+        .arg(
+            Arg::new("sort-by-size-desc")
+                .long("sort-by-size-desc")
+                .help("Sort results by installed size, largest first")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("reverse-deps-count")
+                .long("reverse-deps-count")
+                .help("Sort official results by how many packages depend on them")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["newest", "sort-by-size", "sort-by-size-desc"]),
+        )
+        .arg(
+            Arg::new("merge-arch")
+                .long("merge-arch")
+                .help("Show official packages built for several arches as one row")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("only-installed")
+                .long("only-installed")
+                .help("Only show packages that are installed")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("random")
+                .long("random")
+                .help("Show a random sample of the matches instead of the best ones")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("offline"),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .help("Seed for --random, to get the same sample again")
+                .value_parser(clap::value_parser!(u64))
+                .requires("random"),
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .help("Search cached results only, without using the network")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("no-cache"),
+        )
+        .arg(
+            Arg::new("repo-url-check")
+                .long("repo-url-check")
+                .help("Check that custom official_base_url/aur_base_url respond first")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-banner")
+                .long("no-banner")
+                .help("Don't print the \"Searching ...\" line before searching")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("quiet-errors")
+                .long("quiet-errors")
+                .help("Don't warn when a source fails, e.g. while the AUR is down")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fail-fast")
+                .long("fail-fast")
+                .help("Exit non-zero if any source failed, even when results were shown")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-precheck")
+                .long("no-precheck")
+                .help("Skip the quick connectivity check before searching")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fields")
+                .long("fields")
+                .value_name("LIST")
+                .help("Columns to show, in order (name,version,description,source,updated,size,rdeps,votes,maintainer,outofdate)")
+                .value_parser(parse_fields),
+        )
+        .arg(
+            Arg::new("no-description")
+                .long("no-description")
+                .help("Show only the name, version and source of each result")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("fields"),
+        )
+        .arg(
+            Arg::new("interactive")
+                .short('i')
+                .long("interactive")
+                .help("Pick results with the arrow keys; Space selects several")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("table")
+                .long("table")
+                .help("Show results in a bordered table")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("compact")
+                .long("compact")
+                .help("Show each result's details on indented lines under its name")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("group-by-category")
+                .long("group-by-category")
+                .help("List official results under their package group, e.g. gnome")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["table", "fields", "interactive"]),
+        )
+        .arg(
+            Arg::new("print-url")
+                .long("print-url")
+                .help("Print the selected package's URL instead of installing it")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("case-sensitive")
+                .long("case-sensitive")
+                .help("Match package names case-sensitively when ranking")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("include-testing")
+                .long("include-testing")
+                .help("Include packages from the testing and staging repos")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print results as JSON instead of prompting")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("json-pretty"),
+        )
+        .arg(
+            Arg::new("json-pretty")
+                .long("json-pretty")
+                .help("Print results as indented JSON instead of prompting")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .help("Print results as text, json or csv; json and csv skip the prompt")
+                .value_parser(["text", "json", "csv"])
+                .conflicts_with_all(["json", "json-pretty"]),
+        )
+        .arg(
+            Arg::new("json-array")
+                .long("json-array")
+                .help("Print JSON results as a bare array without the schema wrapper")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rank-debug-export")
+                .long("rank-debug-export")
+                .value_name("PATH")
+                .help("Write every candidate's score breakdown to a CSV or JSON file")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("install-optdepends")
+                .long("install-optdepends")
+                .help("Offer to install optional dependencies after installing")
+                .action(ArgAction::SetTrue),
+        );
 
     let matches = Command::new("archlink")
         .version(VERSION)
         .about("ArchLink helps Arch Linux users to find and install packages")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Log requests; repeat (-vv) to also log response bodies")
+                .action(ArgAction::Count)
+                .global(true),
+        )
+        .arg(
+            Arg::new("lang")
+                .long("lang")
+                .value_name("LANG")
+                .help("Language for messages (defaults to $LANG; only English is bundled)")
+                .global(true),
+        )
+        .arg(
+            Arg::new("no-proxy")
+                .long("no-proxy")
+                .help("Connect directly, ignoring proxy settings")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .help("Disable colored output")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECS")
+                .help("Give up on a request after this many seconds (default 10)")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .global(true),
+        )
+        .arg(
+            Arg::new("test-fixtures")
+                .long("test-fixtures")
+                .value_name("DIR")
+                .help("Read API responses from files in DIR instead of the network (for tests)")
+                .value_parser(clap::value_parser!(PathBuf))
+                .hide(true)
+                .global(true),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Answer yes to all confirmation prompts")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .subcommand(search.clone())
         .subcommand(
-            Command::new("search")
-                .about("Search for packages in official repos and AUR")
-                .arg(
-                    Arg::new("query")
-                        .help("Package name or keyword to search for")
-                        .required(true),
-                ),
+            search
+                .name("discover")
+                .about("List the most voted AUR packages for a keyword")
+                .mut_arg("min-votes", |arg| {
+                    arg.help("Hide AUR packages with fewer than N votes (default 10)")
+                }),
         )
         .subcommand(
             Command::new("install")
                 .about("Install a package directly")
                 .arg(
                     Arg::new("package")
-                        .help("Exact package name to install")
+                        .help("Exact package name to install, or name=version to pin a version")
+                        .required_unless_present("from-file"),
+                )
+                .arg(
+                    Arg::new("from-file")
+                        .long("from-file")
+                        .value_name("PATH")
+                        .help("Install every package listed in a manifest from export-manifest")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .conflicts_with_all([
+                            "package",
+                            "clone-only",
+                            "download-only",
+                            "install-optdepends",
+                        ]),
+                )
+                .arg(
+                    Arg::new("asdeps")
+                        .long("asdeps")
+                        .help("Mark the package as installed as a dependency")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("asexplicit"),
+                )
+                .arg(
+                    Arg::new("asexplicit")
+                        .long("asexplicit")
+                        .help("Mark the package as explicitly installed")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("reinstall")
+                        .long("reinstall")
+                        .help("Install the package even if it is already installed (drops --needed)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("clone-only")
+                        .long("clone-only")
+                        .help("Clone the AUR repo for review instead of installing")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["asdeps", "asexplicit", "reinstall"]),
+                )
+                .arg(
+                    Arg::new("overwrite")
+                        .long("overwrite")
+                        .value_name("GLOB")
+                        .help("Let pacman overwrite conflicting files matching GLOB")
+                        .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                        .conflicts_with("clone-only"),
+                )
+                .arg(
+                    Arg::new("ignore-arch")
+                        .long("ignore-arch")
+                        .help("Install a package built for another architecture")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("clone-only"),
+                )
+                .arg(
+                    Arg::new("download-only")
+                        .long("download-only")
+                        .help("Download an official package into pacman's cache without installing")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all([
+                            "asdeps",
+                            "asexplicit",
+                            "clone-only",
+                            "overwrite",
+                            "ignore-arch",
+                        ]),
+                )
+                .arg(
+                    Arg::new("aur-clone-dir")
+                        .long("aur-clone-dir")
+                        .value_name("DIR")
+                        .help("Directory to clone into with --clone-only")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .requires("clone-only"),
+                )
+                .arg(
+                    Arg::new("install-optdepends")
+                        .long("install-optdepends")
+                        .help("Offer to install optional dependencies after installing")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("compare")
+                .about("Compare two packages side by side")
+                .arg(Arg::new("a").help("First package").required(true))
+                .arg(Arg::new("b").help("Second package").required(true)),
+        )
+        .subcommand(
+            Command::new("files")
+                .about("List the files in a package, or find the package that owns a file")
+                .arg(
+                    Arg::new("target")
+                        .help("Package name, or file path with --owner")
                         .required(true),
+                )
+                .arg(
+                    Arg::new("owner")
+                        .long("owner")
+                        .help("Find the package that owns the given file")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .help("Sync the files database with 'pacman -Fy' first")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove an installed package, warning about what depends on it")
+                .arg(Arg::new("package").help("Installed package name").required(true))
+                .arg(
+                    Arg::new("cascade")
+                        .long("cascade")
+                        .help("Also remove the packages that depend on it (pacman -Rc)")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("why")
+                .about("Show what depends on an installed package")
+                .arg(Arg::new("package").help("Installed package name").required(true)),
+        )
+        .subcommand(
+            Command::new("upgrade")
+                .about("Upgrade the system and AUR packages")
+                .arg(
+                    Arg::new("ignore")
+                        .long("ignore")
+                        .value_name("PKG")
+                        .help("Hold back a package during this upgrade (repeatable)")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("allow-downgrade")
+                        .long("allow-downgrade")
+                        .help("Offer to downgrade packages newer than the repos, after confirming")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("export-manifest")
+                .about("Write the explicitly installed packages to a file")
+                .arg(
+                    Arg::new("path")
+                        .help("File to write the manifest to")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("version").about("Show archlink's version").arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the version and capabilities as JSON")
+                    .action(ArgAction::SetTrue),
+            ),
+        )
+        .subcommand(Command::new("stats").about("Summarize the packages installed on this system"))
+        .subcommand(Command::new("doctor").about("Check that archlink can work on this system"))
+        .subcommand(Command::new("log").about("Show the packages installed through archlink"))
+        .subcommand(
+            Command::new("fav")
+                .about("Keep a list of favorite packages to install together")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Add a package to the favorites")
+                        .arg(Arg::new("package").help("Package name").required(true)),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a package from the favorites")
+                        .arg(Arg::new("package").help("Package name").required(true)),
+                )
+                .subcommand(Command::new("list").about("List the favorites"))
+                .subcommand(Command::new("install").about("Install every favorite")),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Inspect archlink's configuration")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("show")
+                        .about("Print the settings in effect and where each came from"),
+                ),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Inspect the search result cache")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("stats").about("Show the cache's location, size and caps"),
                 ),
         )
+        .subcommand(
+            Command::new("__complete")
+                .about("Print package names starting with a prefix, for shell completion")
+                .hide(true)
+                .arg(Arg::new("partial").default_value("")),
+        )
+        .subcommand(
+            Command::new("self-update").about("Check for a newer archlink and offer to install it"),
+        )
         .get_matches();
 
+    if let Some(warning) = messages::init(matches.get_one::<String>("lang").map(String::as_str)) {
+        eprintln!("{}", warning.yellow());
+    }
+    let config = Config::load();
+    cache::configure(
+        config.cache_dir.clone(),
+        config.cache_max_entries,
+        config.cache_max_bytes,
+    );
+    let max_concurrency = config.max_concurrency.unwrap_or(DEFAULT_MAX_CONCURRENCY);
+    let _ = HTTP_PERMITS.set(Semaphore::new(max_concurrency.max(1)));
+    if let Some(url) = &config.official_base_url {
+        let _ = OFFICIAL_BASE_URL.set(url.trim_end_matches('/').to_string());
+    }
+    if let Some(url) = &config.aur_base_url {
+        let _ = AUR_BASE_URL.set(url.trim_end_matches('/').to_string());
+    }
+    if let Some(order) = config.install_order.clone().filter(|order| !order.is_empty()) {
+        let _ = INSTALL_ORDER.set(order);
+    }
+    if let Some(escalation) = config.escalation {
+        let _ = ESCALATION.set(escalation);
+    }
+    if let Some(dir) = matches.get_one::<PathBuf>("test-fixtures") {
+        let _ = FIXTURE_DIR.set(dir.clone());
+    }
+    let timeout = matches.get_one::<u64>("timeout").map_or(DEFAULT_TIMEOUT, |secs| {
+        Duration::from_secs(*secs)
+    });
+    let _ = REQUEST_TIMEOUT.set(timeout);
+    let no_proxy = matches.get_flag("no-proxy");
+    let client = || network_client(&config, no_proxy, timeout);
+
+    if matches.get_flag("no-color") {
+        colored::control::set_override(false);
+    }
+    ASSUME_YES.store(matches.get_flag("yes"), atomic::Ordering::Relaxed);
+    let mut verbosity = matches.get_count("verbose");
+    if env::var("RUST_LOG").is_ok_and(|level| level.eq_ignore_ascii_case("trace")) {
+        verbosity = verbosity.max(2);
+    }
+    VERBOSITY.store(verbosity, atomic::Ordering::Relaxed);
+    install_interrupt_handler();
+
     match matches.subcommand() {
-        Some(("search", sub_m)) => {
+        Some((command @ ("search" | "discover"), sub_m)) => {
+            let discover = command == "discover";
             let query = sub_m
                 .get_one::<String>("query")
                 .map(|s| s.as_str())
                 .unwrap_or_default()
                 .trim();
             if query.is_empty() {
-                eprintln!("{}", "Error: Query cannot be empty.".red());
+                eprintln!("{}", messages::empty_query().red());
+                std::process::exit(1);
+            }
+            let output = sub_m.get_one::<String>("output").map(String::as_str);
+            if sub_m.get_flag("json-array") && output.is_some_and(|format| format != "json") {
+                eprintln!("{}", messages::json_array_needs_json().red());
+                std::process::exit(1);
+            }
+            let limit = sub_m.get_one::<usize>("limit").copied();
+            let options = SearchOptions {
+                max_results: resolve_max_results(limit, &config),
+                aur_by: sub_m.get_one::<String>("by").cloned(),
+                prefer: sub_m.get_one::<String>("prefer").cloned(),
+                prefer_margin: config.prefer_margin.unwrap_or(DEFAULT_PREFER_MARGIN),
+                trusted_maintainers: config.trusted_maintainers.clone().unwrap_or_default(),
+                no_cache: sub_m.get_flag("no-cache") || FIXTURE_DIR.get().is_some(),
+                print_url: sub_m.get_flag("print-url"),
+                case_sensitive: sub_m.get_flag("case-sensitive"),
+                match_algorithm: config.match_algorithm.unwrap_or_default(),
+                include_testing: sub_m.get_flag("include-testing"),
+                include_flatpak: config.enable_flatpak.unwrap_or(false)
+                    && is_command_in_path("flatpak"),
+                output: if sub_m.get_flag("json-pretty") {
+                    OutputFormat::JsonPretty
+                } else if sub_m.get_flag("json") || sub_m.get_flag("json-array") {
+                    OutputFormat::Json
+                } else {
+                    match output {
+                        Some("json") => OutputFormat::Json,
+                        Some("csv") => OutputFormat::Csv,
+                        _ => OutputFormat::Text,
+                    }
+                },
+                json_array: sub_m.get_flag("json-array"),
+                rank_debug_export: sub_m.get_one::<PathBuf>("rank-debug-export").cloned(),
+                install_optdepends: sub_m.get_flag("install-optdepends"),
+                offline: sub_m.get_flag("offline"),
+                precheck: !sub_m.get_flag("no-precheck") && FIXTURE_DIR.get().is_none(),
+                precheck_ttl: config
+                    .precheck_ttl
+                    .map_or(DEFAULT_PRECHECK_TTL, Duration::from_secs),
+                quiet_errors: sub_m.get_flag("quiet-errors"),
+                fail_fast: sub_m.get_flag("fail-fast"),
+                no_banner: sub_m.get_flag("no-banner"),
+                repo_url_check: sub_m.get_flag("repo-url-check"),
+                timeout,
+                fields: sub_m.get_one::<Vec<Field>>("fields").cloned(),
+                no_description: sub_m.get_flag("no-description"),
+                table: sub_m.get_flag("table"),
+                compact: sub_m.get_flag("compact") || {
+                    let min_width = config.compact_width.unwrap_or(DEFAULT_COMPACT_WIDTH);
+                    io::stdout().is_terminal() && table::terminal_width() < min_width
+                },
+                random_seed: sub_m.get_flag("random").then(|| {
+                    sub_m.get_one::<u64>("seed").copied().unwrap_or_else(time_seed)
+                }),
+                only_installed: sub_m.get_flag("only-installed"),
+                merge_arch: sub_m.get_flag("merge-arch"),
+                group_by_category: sub_m.get_flag("group-by-category"),
+                sort: if sub_m.get_flag("newest") {
+                    SortBy::Newest
+                } else if sub_m.get_flag("sort-by-size") {
+                    SortBy::Smallest
+                } else if sub_m.get_flag("sort-by-size-desc") {
+                    SortBy::Largest
+                } else if sub_m.get_flag("reverse-deps-count") {
+                    SortBy::ReverseDeps
+                } else if sub_m.get_flag("prefix") {
+                    SortBy::Name
+                } else if discover {
+                    SortBy::Votes
+                } else {
+                    SortBy::Relevance
+                },
+                min_votes: sub_m
+                    .get_one::<u64>("min-votes")
+                    .copied()
+                    .unwrap_or(if discover { DISCOVER_MIN_VOTES } else { 0 }),
+                aur_only: discover,
+                match_all: sub_m.get_flag("and"),
+                prefix: sub_m.get_flag("prefix"),
+                ranking: config.ranking.unwrap_or_default(),
+                index_color: resolve_index_color(&config),
+                prompt: config.prompt.clone(),
+                interactive: sub_m.get_flag("interactive") || config.interactive == Some(true),
+            };
+            if options.include_testing {
+                eprintln!("{}", messages::testing_unstable().yellow());
+            }
+            if options.only_installed
+                && let Err(e) = ensure_pacman()
+            {
+                eprintln!("{e}");
                 std::process::exit(1);
             }
-            search_packages(&client, query, max_results).await?;
+            search_packages(&client(), query, &options).await?;
         }
         Some(("install", sub_m)) => {
-            let package = sub_m
+            let install_options = InstallOptions {
+                as_deps: sub_m.get_flag("asdeps"),
+                as_explicit: sub_m.get_flag("asexplicit"),
+                reinstall: sub_m.get_flag("reinstall"),
+                overwrite: sub_m.get_one::<String>("overwrite").cloned(),
+                ignore_arch: sub_m.get_flag("ignore-arch"),
+            };
+            if let Some(glob) = &install_options.overwrite {
+                eprintln!("{}", messages::overwrite_warning(glob).yellow());
+            }
+            if install_options.ignore_arch {
+                eprintln!("{}", messages::ignore_arch_warning(host_arch()).yellow());
+            }
+            if let Some(path) = sub_m.get_one::<PathBuf>("from-file") {
+                let names = match fs::read_to_string(path) {
+                    Ok(contents) => read_manifest(&contents),
+                    Err(e) => {
+                        eprintln!("{}", messages::manifest_read_failed(path.display(), e).red());
+                        std::process::exit(1);
+                    }
+                };
+                if names.is_empty() {
+                    println!("{}", messages::manifest_empty(path.display()).yellow());
+                    return Ok(());
+                }
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                if install_batch(&client(), &names, &install_options).await > 0 {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            let spec = sub_m
                 .get_one::<String>("package")
                 .map(|s| s.as_str())
                 .unwrap_or_default()
                 .trim();
+            let (package, pinned) = match spec.split_once('=') {
+                Some((name, version)) => (name.trim(), Some(version.trim())),
+                None => (spec, None),
+            };
             if package.is_empty() {
-                eprintln!("{}", "Error: Package name cannot be empty.".red());
+                eprintln!("{}", messages::empty_package().red());
+                std::process::exit(1);
+            }
+            if pinned == Some("") {
+                eprintln!("{}", messages::empty_pinned_version(package).red());
                 std::process::exit(1);
             }
-            if let Err(e) = install_package(package, "unknown") {
+            if sub_m.get_flag("clone-only") {
+                let dir = sub_m
+                    .get_one::<PathBuf>("aur-clone-dir")
+                    .cloned()
+                    .or_else(|| config.aur_clone_dir.clone())
+                    .or_else(|| cache::dir().map(|dir| dir.join("aur")));
+                let Some(dir) = dir else {
+                    eprintln!("{}", messages::no_clone_dir().red());
+                    std::process::exit(1);
+                };
+                if let Err(e) = clone_aur(package, &dir) {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            if sub_m.get_flag("download-only") {
+                if pinned.is_some() {
+                    eprintln!("{}", messages::pinned_download(package).red());
+                    std::process::exit(1);
+                }
+                if let Err(e) = download_package(package) {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            let result = match pinned {
+                Some(version) => install_pinned(package, version, &install_options),
+                None => match check_arch(&client(), package, &install_options).await {
+                    Ok(()) => install_package(package, "unknown", &install_options),
+                    Err(e) => Err(e),
+                },
+            };
+            if let Err(e) = result {
                 eprintln!("{e}");
                 std::process::exit(1);
             }
+            if sub_m.get_flag("install-optdepends") {
+                offer_optdepends(&client(), package).await?;
+            }
+        }
+        Some(("compare", sub_m)) => {
+            let a = sub_m.get_one::<String>("a").map(|s| s.trim()).unwrap_or_default();
+            let b = sub_m.get_one::<String>("b").map(|s| s.trim()).unwrap_or_default();
+            if let Err(e) = compare_packages(&client(), a, b).await {
+                eprintln!("{}", messages::error(e).red());
+                std::process::exit(1);
+            }
+        }
+        Some(("files", sub_m)) => {
+            let target = sub_m.get_one::<String>("target").map(|s| s.trim()).unwrap_or_default();
+            let args = if sub_m.get_flag("owner") {
+                ["-F", target]
+            } else {
+                ["-Fl", target]
+            };
+            if let Err(e) = query_files(&args, sub_m.get_flag("refresh")) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(("remove", sub_m)) => {
+            let package = sub_m.get_one::<String>("package").map(|s| s.trim()).unwrap_or_default();
+            if let Err(e) = remove_package(package, sub_m.get_flag("cascade")) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(("why", sub_m)) => {
+            let package = sub_m.get_one::<String>("package").map(|s| s.trim()).unwrap_or_default();
+            if let Err(e) = explain_why(package) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(("upgrade", sub_m)) => {
+            let mut ignored = config.ignore_pkgs.clone().unwrap_or_default();
+            for pkg in sub_m.get_many::<String>("ignore").into_iter().flatten() {
+                if !ignored.contains(pkg) {
+                    ignored.push(pkg.clone());
+                }
+            }
+            if let Err(e) = upgrade_system(&ignored, sub_m.get_flag("allow-downgrade")) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(("export-manifest", sub_m)) => {
+            let path = sub_m.get_one::<PathBuf>("path").expect("path is required");
+            if let Err(e) = export_manifest(path) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(("version", sub_m)) => {
+            if !sub_m.get_flag("json") {
+                println!("archlink {VERSION}");
+                return Ok(());
+            }
+            let mut sources = vec!["official", "aur"];
+            if config.enable_flatpak.unwrap_or(false) && is_command_in_path("flatpak") {
+                sources.push("flatpak");
+            }
+            let info = VersionInfo {
+                name: "archlink",
+                version: VERSION,
+                rustc: env!("ARCHLINK_RUSTC_VERSION"),
+                features: [
+                    ("tui", cfg!(feature = "tui")),
+                    ("table", cfg!(feature = "table")),
+                    ("fuzzy", cfg!(feature = "fuzzy")),
+                ]
+                .into_iter()
+                .filter_map(|(feature, enabled)| enabled.then_some(feature))
+                .collect(),
+                sources,
+            };
+            println!("{}", serde_json::to_string(&info)?);
+        }
+        Some(("stats", _)) => {
+            if let Err(e) = system_stats() {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        Some(("doctor", _)) => {
+            if !doctor(&client()).await {
+                std::process::exit(1);
+            }
+        }
+        Some(("log", _)) => match install_log::read() {
+            Ok(log) if log.is_empty() => println!("{}", messages::install_log_empty()),
+            Ok(log) => print!("{log}"),
+            Err(e) => {
+                eprintln!("{}", messages::error(e).red());
+                std::process::exit(1);
+            }
+        },
+        Some(("fav", sub_m)) => {
+            if let Err(e) = favorites_command(&client, sub_m).await {
+                eprintln!("{}", messages::error(e).red());
+                std::process::exit(1);
+            }
+        }
+        Some(("config", sub_m)) => match sub_m.subcommand() {
+            Some(("show", _)) => {
+                if let Err(e) = show_config(&config) {
+                    eprintln!("{}", messages::error(e).red());
+                    std::process::exit(1);
+                }
+            }
+            _ => unreachable!(),
+        },
+        Some(("cache", sub_m)) => match sub_m.subcommand() {
+            Some(("stats", _)) => match cache::stats() {
+                Some(stats) => println!(
+                    "{}",
+                    messages::cache_stats(
+                        stats.dir.display(),
+                        stats.entries,
+                        stats.max_entries,
+                        &format_size(stats.bytes),
+                        &format_size(stats.max_bytes),
+                    )
+                ),
+                None => {
+                    eprintln!("{}", messages::no_cache_dir().red());
+                    std::process::exit(1);
+                }
+            },
+            _ => unreachable!(),
+        },
+        Some(("__complete", sub_m)) => {
+            let partial = sub_m.get_one::<String>("partial").map(|s| s.trim()).unwrap_or_default();
+            for name in completion_candidates(&client(), partial).await {
+                println!("{name}");
+            }
+        }
+        Some(("self-update", _)) => {
+            self_update(&client()).await?;
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Proxy precedence: `--no-proxy`, then `proxy` in the config (skipping the
+/// hosts in `no_proxy`, or `NO_PROXY`), then reqwest's own handling of
+/// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`.
+/// The HTTP client, built only by commands that reach the network, so a
+/// base URL that isn't HTTPS, or a proxy that doesn't parse, is refused
+/// there while `version`, `log` and the rest still run.
+fn network_client(config: &Config, no_proxy: bool, timeout: Duration) -> Client {
+    let allow_insecure = config.allow_insecure == Some(true);
+    for (key, url) in [
+        ("official_base_url", &config.official_base_url),
+        ("aur_base_url", &config.aur_base_url),
+    ] {
+        if let Some(url) = url
+            && !allow_insecure
+            && !url.starts_with("https://")
+        {
+            eprintln!("{}", messages::insecure_base_url(key, url).red());
+            std::process::exit(1);
+        }
+    }
+    build_client(config, no_proxy, timeout).unwrap_or_else(|e| {
+        eprintln!("{}", messages::error(e).red());
+        std::process::exit(1);
+    })
+}
+
+fn build_client(config: &Config, no_proxy: bool, timeout: Duration) -> reqwest::Result<Client> {
+    let user_agent = match &config.user_agent {
+        Some(user_agent) => user_agent.clone(),
+        None => format!("archlink/{VERSION}"),
+    };
+    // Redirects are followed as reqwest would, up to 10, but never from
+    // HTTPS to plain HTTP unless `allow_insecure` is set.
+    let allow_insecure = config.allow_insecure == Some(true);
+    let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+        let downgrade = attempt.url().scheme() == "http"
+            && attempt.previous().last().is_some_and(|url| url.scheme() == "https");
+        if downgrade && !allow_insecure {
+            let error = messages::insecure_redirect(attempt.url().as_str());
+            attempt.error(error)
+        } else if attempt.previous().len() >= 10 {
+            attempt.error(messages::too_many_redirects())
+        } else {
+            attempt.follow()
+        }
+    });
+    let mut builder = Client::builder()
+        .timeout(timeout)
+        .user_agent(user_agent)
+        .redirect(redirects);
+    if no_proxy {
+        builder = builder.no_proxy();
+    } else if let Some(url) = &config.proxy {
+        let bypass = match &config.no_proxy {
+            Some(hosts) => reqwest::NoProxy::from_string(hosts),
+            None => reqwest::NoProxy::from_env(),
+        };
+        builder = builder.proxy(reqwest::Proxy::all(url)?.no_proxy(bypass));
+    }
+    builder.build()
+}
+
+/// The selection number color from the config, or `None` if numbers are
+/// turned off. Unknown color names fall back to white with a warning.
+fn resolve_index_color(config: &Config) -> Option<Color> {
+    if config.show_index == Some(false) {
+        return None;
+    }
+    let Some(name) = &config.number_color else {
+        return Some(Color::White);
+    };
+    Some(name.parse().unwrap_or_else(|()| {
+        eprintln!("{}", messages::invalid_number_color(name).yellow());
+        Color::White
+    }))
+}
+
+/// Package names starting with `partial` for the completion scripts:
+/// official packages from the local sync databases, then AUR names from the
+/// RPC's suggest endpoint, with a short timeout. Results are cached like
+/// searches, unless the AUR didn't answer.
+async fn completion_candidates(client: &Client, partial: &str) -> Vec<String> {
+    let key = cache::completion_key(partial);
+    if let Some(cached) = cache::load(&key, false) {
+        return cached.packages.into_iter().map(|pkg| pkg.name).collect();
+    }
+
+    let mut names: Vec<String> = SysCommand::new("pacman")
+        .arg("-Slq")
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|name| name.starts_with(partial))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.dedup();
+
+    // The AUR only suggests for two or more characters.
+    let mut complete = true;
+    if partial.chars().count() >= 2 {
+        let url = format!(
+            "{}/rpc/?v=5&type=suggest&arg={}",
+            aur_base_url(),
+            urlencoding::encode(partial)
+        );
+        let response = client.get(&url).timeout(COMPLETE_TIMEOUT).send().await;
+        match response {
+            Ok(response) => match response.json::<Vec<String>>().await {
+                Ok(aur) => {
+                    for name in aur {
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                    }
+                }
+                Err(_) => complete = false,
+            },
+            Err(_) => complete = false,
+        }
+    }
+    names.truncate(COMPLETE_LIMIT);
+
+    if complete {
+        let ranked = RankedResults {
+            packages: names
+                .iter()
+                .map(|name| Package {
+                    name: name.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        cache::store(&key, &ranked);
+    }
+    names
+}
+
+/// Prints every setting that has a value, set or default, as TOML. Each line
+/// ends in a comment saying whether the value came from the config file, the
+/// environment or the built-in defaults.
+fn show_config(config: &Config) -> Result<(), toml::ser::Error> {
+    let file = fs::read_to_string(CONFIG_PATH).ok();
+    // A file that doesn't load as a `Config` was ignored, so none of its
+    // keys took effect.
+    let file_keys: Vec<String> = file
+        .as_deref()
+        .filter(|contents| toml::from_str::<Config>(contents).is_ok())
+        .and_then(|contents| contents.parse::<toml::Table>().ok())
+        .map(|table| table.keys().cloned().collect())
+        .unwrap_or_default();
+    let mut effective = toml::Table::try_from(config)?;
+    effective.insert(
+        "max_results".to_string(),
+        toml::Value::from(resolve_max_results(None, config) as i64),
+    );
+    let mut defaults = vec![
+        ("max_concurrency", toml::Value::from(DEFAULT_MAX_CONCURRENCY as i64)),
+        ("prefer_margin", toml::Value::from(i64::from(DEFAULT_PREFER_MARGIN))),
+        ("precheck_ttl", toml::Value::from(DEFAULT_PRECHECK_TTL.as_secs() as i64)),
+        ("compact_width", toml::Value::from(DEFAULT_COMPACT_WIDTH as i64)),
+        ("escalation", toml::Value::from("sudo")),
+        ("ranking", toml::Value::try_from(RankingWeights::default())?),
+        ("user_agent", toml::Value::from(format!("archlink/{VERSION}"))),
+        ("cache_max_entries", toml::Value::from(cache::DEFAULT_MAX_ENTRIES as i64)),
+        ("cache_max_bytes", toml::Value::from(cache::DEFAULT_MAX_BYTES as i64)),
+    ];
+    if let Some(dir) = cache::dir() {
+        defaults.push(("cache_dir", toml::Value::from(dir.display().to_string())));
+    }
+    for (key, value) in defaults {
+        effective.entry(key).or_insert(value);
+    }
+
+    println!("{}", messages::config_show_header(CONFIG_PATH, file.is_some()).dimmed());
+    for (key, value) in &effective {
+        let origin = if key == "max_results" && config.max_results_from_env {
+            messages::config_from_env("ARCHLINK_MAX_RESULTS")
+        } else if file_keys.contains(key) {
+            messages::config_from_file()
+        } else {
+            messages::config_from_default()
+        };
+        println!("{key} = {value} {}", format!("# {origin}").dimmed());
+    }
+    Ok(())
+}
+
+/// Picks the result limit: `--limit`, then `ARCHLINK_MAX_RESULTS`, then the
+/// config file, then the built-in default. `Config::load` has already
+/// applied the environment variable.
+fn resolve_max_results(limit: Option<usize>, config: &Config) -> usize {
+    limit.or(config.max_results).unwrap_or(DEFAULT_MAX_RESULTS)
+}
+
+/// Restores the terminal and exits with the usual SIGINT status on Ctrl-C,
+/// so an interrupted prompt or progress line never leaves the cursor hidden
+/// or a color applied. Nothing is written when stdout isn't a terminal.
+fn install_interrupt_handler() {
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let mut stdout = io::stdout();
+            if stdout.is_terminal() {
+                let _ = writeln!(stdout, "\x1b[0m\x1b[?25h");
+                let _ = stdout.flush();
+            }
+            std::process::exit(130);
+        }
+    });
+}
+
+async fn search_packages(
+    client: &Client,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cache_key = cache::key(query, options);
+    // Cached results are already truncated, so an export needs a fresh fetch.
+    let cached = if options.offline {
+        cache::load(&cache_key, true)
+    } else if options.no_cache
+        || options.rank_debug_export.is_some()
+        || options.random_seed.is_some()
+    {
+        None
+    } else {
+        cache::load(&cache_key, false)
+    };
+    let mut reports = Vec::new();
+    let (ranked, cache_status) = match cached {
+        Some(ranked) => (ranked, "hit"),
+        None if options.offline => {
+            eprintln!("{}", messages::no_cached_results(query).yellow());
+            return Ok(());
+        }
+        None => {
+            if options.repo_url_check
+                && let Err(e) = check_base_urls(client).await
+            {
+                eprintln!("{}", e.red());
+                std::process::exit(1);
+            }
+            if options.precheck && !is_online(client, options).await {
+                eprintln!("{}", messages::appears_offline().red());
+                std::process::exit(1);
+            }
+            let ranked;
+            (ranked, reports) = fetch_results(client, query, options).await;
+            let complete = reports.iter().all(|report| report.outcome.is_ok());
+            let cache_status = if options.no_cache || options.random_seed.is_some() {
+                "disabled"
+            } else if complete {
+                cache::store(&cache_key, &ranked);
+                "miss, stored"
+            } else {
+                "miss, not stored since a source failed"
+            };
+            (ranked, cache_status)
+        }
+    };
+    let all_results = &ranked.packages;
+    if VERBOSITY.load(atomic::Ordering::Relaxed) >= 1 {
+        print_search_summary(&reports, cache_status, &ranked);
+    }
+
+    match options.output {
+        OutputFormat::Text => {}
+        OutputFormat::Csv => {
+            print_csv(all_results);
+            exit_if_incomplete(&reports, options);
+            return Ok(());
+        }
+        OutputFormat::Json | OutputFormat::JsonPretty => {
+            print_json(query, all_results, options)?;
+            exit_if_incomplete(&reports, options);
+            return Ok(());
+        }
+    }
+
+    if all_results.is_empty() {
+        // A source that answered with nothing is not a failure; say so, since
+        // failed sources were already warned about.
+        let mut searched = Vec::new();
+        let mut failed = Vec::new();
+        for report in &reports {
+            if report.outcome == Ok(0) {
+                println!("{}", messages::source_returned_nothing(report.name).dimmed());
+                searched.push(report.name);
+            } else {
+                failed.push(report.name);
+            }
+        }
+        if failed.is_empty() {
+            println!("{}", messages::no_packages_found(query).yellow());
+        } else {
+            let note = messages::no_packages_found_incomplete(query, &failed, &searched);
+            println!("{}", note.yellow());
+        }
+        exit_if_incomplete(&reports, options);
+        return Ok(());
+    }
+
+    // Grouping reorders the results, and the numbers shown and picked follow
+    // the grouped order.
+    let grouped;
+    let mut sections = Vec::new();
+    let all_results = if options.group_by_category {
+        (grouped, sections) = group_by_category(all_results);
+        &grouped
+    } else {
+        all_results
+    };
+
+    // Counted after truncation, so they add up to the list below.
+    let counts: Vec<(&str, usize)> = ["official", "aur", "flatpak"]
+        .into_iter()
+        .map(|source| (source, all_results.iter().filter(|pkg| pkg.source == source).count()))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    println!("{}", messages::suggestions_header(query, &counts).bold().white());
+    // Builds without the `tui` feature fall back to the numbered prompt.
+    #[cfg(feature = "tui")]
+    let interactive = options.interactive && picker::available();
+    #[cfg(not(feature = "tui"))]
+    let interactive = false;
+    match &options.fields {
+        _ if interactive => {}
+        _ if options.group_by_category => {
+            let mut rows = all_results.iter().enumerate();
+            for (heading, count) in &sections {
+                println!("{}", format!("{heading}:").bold().white());
+                for (i, pkg) in rows.by_ref().take(*count) {
+                    print_row(i, pkg, query, options);
+                }
+            }
+        }
+        #[cfg(feature = "table")]
+        _ if options.table => {
+            let fields = options.fields.clone().unwrap_or_else(|| default_fields(options));
+            print_table(all_results, &fields, options.index_color)
+        }
+        Some(fields) => print_fields(all_results, fields, options.index_color),
+        None if options.no_description
+            || !matches!(options.sort, SortBy::Relevance | SortBy::Name) =>
+        {
+            print_fields(all_results, &default_fields(options), options.index_color)
+        }
+        None => {
+            for (i, pkg) in all_results.iter().enumerate() {
+                print_row(i, pkg, query, options);
+            }
+        }
+    }
+
+    let total = ranked.official_total + ranked.aur_total + ranked.flatpak_total;
+    if all_results.len() < total {
+        let note = messages::truncation_note(
+            all_results.len(),
+            total,
+            ranked.official_total,
+            ranked.aur_total,
+            ranked.flatpak_total,
+        );
+        println!("{}", note.yellow());
+    }
+    let failed: Vec<&str> = reports
+        .iter()
+        .filter(|report| report.outcome.is_err())
+        .map(|report| report.name)
+        .collect();
+    if !failed.is_empty() {
+        println!("{}", messages::results_incomplete(&failed).yellow());
+    }
+    // Scripts get the status before the prompt could wait for input.
+    exit_if_incomplete(&reports, options);
+
+    #[cfg(feature = "tui")]
+    if interactive {
+        let items: Vec<String> = all_results
+            .iter()
+            .map(|pkg| {
+                let source = source_label(pkg);
+                let name = display_name(pkg);
+                let badge = if pkg.trusted {
+                    format!(" [{}]", messages::trusted_badge())
+                } else {
+                    String::new()
+                };
+                if options.no_description {
+                    format!("{name} {} [{source}]{badge}", pkg.version)
+                } else {
+                    format!("{name} {} [{source}]{badge} - {}", pkg.version, pkg.description)
+                }
+            })
+            .collect();
+        for i in picker::pick(&items)? {
+            act_on_selection(client, &all_results[i], options).await?;
+        }
+        return Ok(());
+    }
+
+    // Without numbers there is nothing to select.
+    if options.index_color.is_none() {
+        return Ok(());
+    }
+    let prompt = match &options.prompt {
+        Some(prompt) => prompt.replace("{count}", &all_results.len().to_string()),
+        None => messages::select_prompt(options.print_url),
+    };
+    print!("{}", prompt.bold().white());
+    io::stdout().flush()?;
+    let input = read_answer().unwrap_or_default();
+    let choice = input.trim().parse::<usize>().unwrap_or(0);
+
+    if choice > 0 && choice <= all_results.len() {
+        act_on_selection(client, &all_results[choice - 1], options).await?;
+    } else if choice != 0 {
+        println!("{}", messages::invalid_selection().yellow());
+    }
+
+    Ok(())
+}
+
+/// Exits with status 1 under `--fail-fast` if a source failed, after the
+/// results from the others have been shown.
+fn exit_if_incomplete(reports: &[SourceReport], options: &SearchOptions) {
+    if options.fail_fast && reports.iter().any(|report| report.outcome.is_err()) {
+        let _ = io::stdout().flush();
+        std::process::exit(1);
+    }
+}
+
+/// Prints the URLs of, or offers to install, a package picked from the
+/// results.
+async fn act_on_selection(
+    client: &Client,
+    selected_package: &Package,
+    options: &SearchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.print_url {
+        for url in package_urls(selected_package) {
+            println!("{url}");
+        }
+    } else if confirm(&messages::install_confirm(&selected_package.name), false)? {
+        let install_options = InstallOptions::default();
+        let result = match selected_package.source {
+            "aur" => check_arch(client, &selected_package.name, &install_options).await,
+            _ => Ok(()),
+        };
+        if let Err(e) = result.and_then(|()| {
+            install_package(&selected_package.name, selected_package.source, &install_options)
+        }) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        if options.install_optdepends {
+            offer_optdepends(client, &selected_package.name).await?;
+        }
+        offer_favorite(&selected_package.name)?;
+    } else {
+        println!("{}", messages::install_cancelled().yellow());
+    }
+    Ok(())
+}
+
+/// Asks whether to add a package just installed from a search to the
+/// favorites. `--yes` skips the question rather than answering it, so
+/// scripted installs never change the list.
+fn offer_favorite(package: &str) -> io::Result<()> {
+    if ASSUME_YES.load(atomic::Ordering::Relaxed)
+        || favorites::read()?.iter().any(|favorite| favorite == package)
+    {
+        return Ok(());
+    }
+    if confirm(&messages::add_favorite_confirm(package), false)? && favorites::add(package)? {
+        println!("{}", messages::favorite_added(package).green());
+    }
+    Ok(())
+}
+
+/// One result in the default layout; `i` is its position in the list. The
+/// compact layout puts the name on a line of its own, so nothing is padded.
+/// A package named exactly like the query, ignoring case, is marked with `►`
+/// and drawn bold, so it stands out among fuzzier matches even without
+/// colors.
+fn print_row(i: usize, pkg: &Package, query: &str, options: &SearchOptions) {
+    let index = match options.index_color {
+        Some(color) => format!("{}. ", (i + 1).to_string().bold().color(color)),
+        None => String::new(),
+    };
+    let badge = if pkg.trusted {
+        format!(" [{}]", messages::trusted_badge().magenta())
+    } else {
+        String::new()
+    };
+    let name = if pkg.name.eq_ignore_ascii_case(query.trim()) {
+        format!("► {}", display_name(pkg)).bright_green().bold()
+    } else {
+        display_name(pkg).green()
+    };
+    if options.no_description {
+        println!(
+            "{index}{name:<30} {:<15} [{}]{badge}",
+            pkg.version.blue(),
+            source_label(pkg).cyan()
+        );
+        return;
+    }
+    if options.compact {
+        println!("{index}{name}");
+        println!("    {} [{}]{badge}", pkg.version.blue(), source_label(pkg).cyan());
+        println!("    {}", pkg.description);
+        return;
+    }
+    println!(
+        "{index}{name:<30} {:<15} - {} [{}]{badge}",
+        pkg.version.blue(),
+        pkg.description,
+        source_label(pkg).cyan()
+    );
+}
+
+/// Orders results for `--group-by-category`: official packages under their
+/// first group, groups in the order of their best-ranked package, then
+/// official packages without a group, then each other source on its own.
+/// Rank order is kept within a section. Returns the reordered packages and
+/// each section's heading and size.
+fn group_by_category(packages: &[Package]) -> (Vec<Package>, Vec<(String, usize)>) {
+    // (kind, heading, packages), where kind orders groups, then ungrouped,
+    // then other sources.
+    let mut sections: Vec<(u8, String, Vec<Package>)> = Vec::new();
+    for pkg in packages {
+        let (kind, heading) = match (pkg.source, pkg.groups.first()) {
+            ("official", Some(group)) => (0, group.clone()),
+            ("official", None) => (1, messages::ungrouped()),
+            (source, _) => (2, messages::no_groups_section(source)),
+        };
+        match sections.iter_mut().find(|(k, h, _)| *k == kind && *h == heading) {
+            Some((_, _, section)) => section.push(pkg.clone()),
+            None => sections.push((kind, heading, vec![pkg.clone()])),
+        }
+    }
+    sections.sort_by_key(|(kind, _, _)| *kind);
+
+    let headings = sections
+        .iter()
+        .map(|(_, heading, pkgs)| (heading.clone(), pkgs.len()))
+        .collect();
+    let packages = sections.into_iter().flat_map(|(_, _, pkgs)| pkgs).collect();
+    (packages, headings)
+}
+
+/// The columns shown without `--fields`, minus the description for
+/// `--no-description`.
+fn default_fields(options: &SearchOptions) -> Vec<Field> {
+    Field::defaults(options.sort)
+        .iter()
+        .copied()
+        .filter(|field| !options.no_description || *field != Field::Description)
+        .collect()
+}
+
+/// The name as listed, followed by its arches if `--merge-arch` merged
+/// several, e.g. `firefox [x86_64, any]`.
+fn display_name(pkg: &Package) -> String {
+    if pkg.arches.len() > 1 {
+        format!("{} [{}]", pkg.name, pkg.arches.join(", "))
+    } else {
+        pkg.name.clone()
+    }
+}
+
+/// The source shown next to a result, naming the repo for testing packages
+/// and marking AUR VCS packages, whose versions don't track upstream.
+fn source_label(pkg: &Package) -> String {
+    match pkg.repo.as_deref() {
+        Some(repo) if is_testing_repo(repo) => format!("{}: {repo}", pkg.source),
+        _ if pkg.source == "aur" && is_vcs_package(&pkg.name) => format!("{}: vcs", pkg.source),
+        _ => pkg.source.to_string(),
+    }
+}
+
+/// Prints results with only the chosen columns, each padded to its widest
+/// value. The description is never padded since nothing follows it.
+fn print_fields(results: &[Package], fields: &[Field], index_color: Option<Color>) {
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|pkg| fields.iter().map(|field| field.value(pkg)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..fields.len())
+        .map(|col| rows.iter().map(|row| row[col].chars().count()).max().unwrap_or(0))
+        .collect();
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut cells = Vec::new();
+        if let Some(color) = index_color {
+            cells.push(format!("{}.", (i + 1).to_string().bold().color(color)));
+        }
+        for (col, (field, value)) in fields.iter().zip(row).enumerate() {
+            let width = if col + 1 == fields.len() { 0 } else { widths[col] };
+            cells.push(format!("{:<width$}", field.paint(value)));
+        }
+        println!("{}", cells.join(" ").trim_end());
+    }
+}
+
+/// Prints results as a bordered table, wrapping descriptions that would run
+/// past the terminal edge.
+#[cfg(feature = "table")]
+fn print_table(results: &[Package], fields: &[Field], index_color: Option<Color>) {
+    let labels = messages::table_headers();
+    // With numbers shown, column 0 is the selection number.
+    let offset = usize::from(index_color.is_some());
+    let mut headers = labels[..offset].to_vec();
+    headers.extend(fields.iter().map(|field| match field {
+        Field::Name => labels[1],
+        Field::Version => labels[2],
+        Field::Description => labels[3],
+        Field::Source => labels[4],
+        Field::Updated => labels[5],
+        Field::Size => labels[6],
+        Field::ReverseDeps => labels[7],
+        Field::Votes => labels[8],
+        Field::Maintainer => labels[9],
+        Field::OutOfDate => labels[10],
+    }));
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .enumerate()
+        .map(|(i, pkg)| {
+            let mut row = vec![(i + 1).to_string(); offset];
+            row.extend(fields.iter().map(|field| field.value(pkg)));
+            row
+        })
+        .collect();
+    let wrap = fields
+        .iter()
+        .position(|field| *field == Field::Description)
+        .map(|i| i + offset);
+
+    let lines = table::render(&headers, &rows, wrap, |col, text| match index_color {
+        Some(color) if col == 0 => text.bold().color(color).to_string(),
+        _ => fields[col - offset].paint(text).to_string(),
+    });
+    for line in lines {
+        println!("{line}");
+    }
+}
+
+/// A quick HEAD request to archlinux.org (or `official_base_url`), so being
+/// offline is reported in seconds instead of after both searches time out.
+/// Any HTTP response, whatever its status, counts as online. A pass is
+/// remembered in the state dir for `precheck_ttl`, so back-to-back searches
+/// only pay for the first check.
+async fn is_online(client: &Client, options: &SearchOptions) -> bool {
+    let marker = state_dir().map(|dir| dir.join("last-online"));
+    let last_online = marker
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| contents.trim().parse::<i64>().ok());
+    let ttl = options.precheck_ttl.as_secs() as i64;
+    if last_online.is_some_and(|at| (0..ttl).contains(&(dates::now() - at))) {
+        return true;
+    }
+
+    let url = format!("{}/", official_base_url());
+    let online = responds(client, &url, PRECHECK_TIMEOUT.min(options.timeout))
+        .await
+        .is_ok();
+    if online && let Some(path) = marker {
+        // Best-effort, like the cache.
+        let _ = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| write_atomic(&path, dates::now().to_string()));
+    }
+    online
+}
+
+/// Whether `url` answers a HEAD request at all, whatever the status.
+async fn responds(
+    client: &Client,
+    url: &str,
+    timeout: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _permit = http_permit().await?;
+    client.head(url).timeout(timeout).send().await?;
+    Ok(())
+}
+
+fn official_base_url() -> &'static str {
+    OFFICIAL_BASE_URL.get().map_or(DEFAULT_OFFICIAL_BASE_URL, String::as_str)
+}
+
+fn aur_base_url() -> &'static str {
+    AUR_BASE_URL.get().map_or(DEFAULT_AUR_BASE_URL, String::as_str)
+}
+
+/// The URLs `is_online` and `doctor` probe, one per API archlink uses.
+fn probe_urls() -> [(&'static str, String); 2] {
+    [
+        ("official_base_url", format!("{}/", official_base_url())),
+        ("aur_base_url", format!("{}/rpc/?v=5&type=info", aur_base_url())),
+    ]
+}
+
+/// `search --repo-url-check`: fails fast, naming the config key, if a
+/// custom base URL doesn't respond. The default hosts are not checked, so
+/// this costs nothing without a custom mirror.
+async fn check_base_urls(client: &Client) -> Result<(), String> {
+    let custom = [
+        official_base_url() != DEFAULT_OFFICIAL_BASE_URL,
+        aur_base_url() != DEFAULT_AUR_BASE_URL,
+    ];
+    for ((key, url), custom) in probe_urls().into_iter().zip(custom) {
+        if custom && let Err(e) = responds(client, &url, PRECHECK_TIMEOUT).await {
+            return Err(messages::base_url_unreachable(key, &url, e));
+        }
+    }
+    Ok(())
+}
+
+/// Waits for a free request slot; drop the permit once the response is read.
+async fn http_permit() -> Result<SemaphorePermit<'static>, AcquireError> {
+    HTTP_PERMITS
+        .get_or_init(|| Semaphore::new(DEFAULT_MAX_CONCURRENCY))
+        .acquire()
+        .await
+}
+
+fn print_json(
+    query: &str,
+    results: &[Package],
+    options: &SearchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = JsonReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        query,
+        results,
+    };
+    let pretty = options.output == OutputFormat::JsonPretty;
+    let json = match (options.json_array, pretty) {
+        (true, true) => serde_json::to_string_pretty(results)?,
+        (true, false) => serde_json::to_string(results)?,
+        (false, true) => serde_json::to_string_pretty(&report)?,
+        (false, false) => serde_json::to_string(&report)?,
+    };
+    println!("{json}");
+    Ok(())
+}
+
+/// Prints results as CSV with a header row, in rank order. Fields that are
+/// unknown, such as an official package's votes, are left empty.
+fn print_csv(results: &[Package]) {
+    // RFC 4180 ends every row, the header included, with CRLF.
+    print!("name,version,source,repo,description,votes,last_updated,installed_size\r\n");
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    for pkg in results {
+        print!(
+            "{},{},{},{},{},{},{},{}\r\n",
+            csv_field(&pkg.name),
+            csv_field(&pkg.version),
+            csv_field(pkg.source),
+            csv_field(pkg.repo.as_deref().unwrap_or_default()),
+            csv_field(&pkg.description),
+            optional(pkg.votes.map(|votes| votes.to_string())),
+            optional(pkg.last_updated.map(dates::format_timestamp)),
+            optional(pkg.installed_size.map(|size| size.to_string()))
+        );
+    }
+}
+
+/// How one source fared during a search, for the `-v` summary.
+struct SourceReport {
+    name: Source,
+    elapsed: Duration,
+    /// The number of results, or why the search failed.
+    outcome: Result<usize, String>,
+}
+
+/// The `-v` diagnostics printed after a search.
+fn print_search_summary(reports: &[SourceReport], cache_status: &str, ranked: &RankedResults) {
+    let log = |line: String| eprintln!("{}", line.dimmed());
+    for report in reports {
+        let ms = report.elapsed.as_millis();
+        match &report.outcome {
+            Ok(count) => log(messages::summary_source_log(report.name, *count, ms)),
+            Err(e) => log(messages::summary_source_failed_log(report.name, ms, e)),
+        }
+    }
+    log(messages::summary_cache_log(cache_status));
+    let total = ranked.official_total + ranked.aur_total + ranked.flatpak_total;
+    log(messages::summary_shown_log(ranked.packages.len(), total));
+}
+
+/// Awaits `future` while a spinner on stderr shows the time spent against
+/// the request timeout, so a slow search visibly counts down to giving up.
+/// Skipped unless stderr is a terminal, and at `-v`, where it would garble
+/// the log lines.
+async fn with_spinner<T>(future: impl Future<Output = T>, budget: Duration) -> T {
+    if !io::stderr().is_terminal() || VERBOSITY.load(atomic::Ordering::Relaxed) >= 1 {
+        return future.await;
+    }
+    const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+    let started = Instant::now();
+    let mut future = std::pin::pin!(future);
+    let mut ticker = tokio::time::interval(Duration::from_millis(100));
+    let mut frame = 0;
+    let output = loop {
+        tokio::select! {
+            output = &mut future => break output,
+            _ = ticker.tick() => {
+                let elapsed = started.elapsed().min(budget);
+                let progress = messages::search_progress(elapsed.as_secs_f64(), budget.as_secs());
+                eprint!("\r{} {}", FRAMES[frame % FRAMES.len()], progress.dimmed());
+                frame += 1;
+            }
+        }
+    };
+    eprint!("\r\x1b[K");
+    output
+}
+
+/// Queries the enabled sources and ranks the combined results, reporting how
+/// each source fared. Failed sources are warned about and left out.
+async fn fetch_results(
+    client: &Client,
+    query: &str,
+    options: &SearchOptions,
+) -> (RankedResults, Vec<SourceReport>) {
+    if options.output == OutputFormat::Text && !options.no_banner {
+        let banner = if options.aur_by.as_deref() == Some("maintainer") {
+            messages::searching_by_maintainer()
+        } else if options.aur_only {
+            messages::searching_aur()
+        } else {
+            messages::searching_all()
+        };
+        println!("{}", banner.bold().white());
+    }
+
+    let sources = sources::configured(options);
+    let searches = sources::join_all(
+        sources
+            .iter()
+            .map(|source| {
+                Box::pin(async move {
+                    let started = Instant::now();
+                    let outcome = source.search(client, query).await;
+                    (outcome, started.elapsed())
+                }) as Pin<Box<dyn Future<Output = (sources::SearchResult, Duration)>>>
+            })
+            .collect(),
+    );
+    let outcomes = if options.output == OutputFormat::Text {
+        with_spinner(searches, options.timeout).await
+    } else {
+        searches.await
+    };
+
+    let mut reports = Vec::new();
+    let mut candidates = Vec::new();
+    for (source, (outcome, elapsed)) in sources.iter().zip(outcomes) {
+        let outcome = match outcome {
+            Ok(packages) => {
+                let count = packages.len();
+                candidates.extend(packages);
+                Ok(count)
+            }
+            Err(e) => {
+                if !options.quiet_errors {
+                    eprintln!("{}", messages::search_failed(source.name(), &e).yellow());
+                }
+                Err(e.to_string())
+            }
+        };
+        reports.push(SourceReport {
+            name: source.name(),
+            elapsed,
+            outcome,
+        });
+    }
+    // Flatpak is searched locally, so a mirror wouldn't help it.
+    let slow: Vec<&str> = reports
+        .iter()
+        .filter(|report| report.outcome.is_ok() && report.elapsed > SLOW_SOURCE_THRESHOLD)
+        .map(|report| report.name)
+        .filter(|name| *name != "flatpak")
+        .collect();
+    // Unlike a failure, a slow answer still gave results, so
+    // `--quiet-errors` leaves the hint alone.
+    if !slow.is_empty() {
+        eprintln!("{}", messages::slow_sources(&slow, SLOW_SOURCE_THRESHOLD.as_secs()).yellow());
+    }
+
+    if options.min_votes > 0 {
+        candidates.retain(|pkg| meets_min_votes(pkg, options.min_votes));
+    }
+    if options.prefix {
+        let prefix = query.trim().to_lowercase();
+        candidates.retain(|pkg| pkg.name.to_lowercase().starts_with(&prefix));
+    }
+    if options.match_all {
+        candidates.retain(|pkg| matches_every_word(pkg, query, options.case_sensitive));
+    }
+    if options.only_installed {
+        let installed = installed_packages();
+        candidates.retain(|pkg| installed.contains(&pkg.name));
+    }
+    // Marked before the export, whose breakdown includes `TRUSTED_BOOST`.
+    for pkg in &mut candidates {
+        pkg.trusted = is_trusted(pkg, &options.trusted_maintainers);
+    }
+
+    if let Some(path) = &options.rank_debug_export {
+        let candidates: Vec<&Package> = candidates.iter().collect();
+        if let Err(e) = export_rank_debug(path, &candidates, query, options) {
+            eprintln!("{}", messages::rank_export_failed(e).yellow());
+        }
+    }
+
+    (rank_results(candidates, query, options), reports)
+}
+
+/// Testing, staging and unstable repos carry packages that haven't reached
+/// the stable repos yet.
+fn is_testing_repo(repo: &str) -> bool {
+    ["testing", "staging", "unstable"]
+        .iter()
+        .any(|suffix| repo == *suffix || repo.ends_with(&format!("-{suffix}")))
+}
+
+/// AUR packages built from a repository's latest commit, going by the
+/// naming convention. Their version is only the one the PKGBUILD was last
+/// published with, so comparing it says little about what a build installs.
+fn is_vcs_package(name: &str) -> bool {
+    ["git", "svn", "hg", "bzr", "darcs", "fossil", "cvs"]
+        .iter()
+        .any(|suffix| name.ends_with(&format!("-{suffix}")))
+}
+
+/// Fetches `url` and parses the body as JSON, logging the request under `-v`
+/// and the raw body under `-vv` so schema changes can be told apart from
+/// network failures.
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &Client,
+    url: &str,
+) -> Result<T, Box<dyn std::error::Error>> {
+    if let Some(dir) = FIXTURE_DIR.get() {
+        return read_fixture(dir, url);
+    }
+    let deadline = Instant::now() + REQUEST_TIMEOUT.get().copied().unwrap_or(DEFAULT_TIMEOUT);
+    let mut retried = false;
+    let (status, started, body) = loop {
+        let permit = http_permit().await?;
+        let started = Instant::now();
+        let mut request = client.get(url);
+        if retried {
+            request = request.timeout(deadline.saturating_duration_since(started));
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            break (status, started, response.text().await?);
+        }
+
+        // Retry-After may also be an HTTP date; only the seconds form is used.
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        log_http(url, status, started, 0);
+        let host = response.url().host_str().unwrap_or_default().to_string();
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match retry_after.map(Duration::from_secs) {
+            Some(wait) if !retried && wait < remaining => {
+                retried = true;
+                // Other requests may run while this one waits.
+                drop(permit);
+                tokio::time::sleep(wait).await;
+            }
+            _ => return Err(messages::rate_limited(&host, retry_after).into()),
+        }
+    };
+
+    log_http(url, status, started, body.len());
+    if VERBOSITY.load(atomic::Ordering::Relaxed) >= 2 {
+        let mut end = body.len().min(HTTP_LOG_BODY_LIMIT);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let ellipsis = if end < body.len() { "..." } else { "" };
+        let shown = format!("{}{ellipsis}", &body[..end]);
+        eprintln!("{}", messages::http_body_log(&shown).dimmed());
+    }
+
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The canned response for `url` under `--test-fixtures`. The file is named
+/// after the URL's path and query, with everything but letters, digits, `-`
+/// and `.` turned into `_`, so the host and base URL don't matter: the
+/// official search for `vim` reads `packages_search_json__q_vim.json`.
+fn read_fixture<T: serde::de::DeserializeOwned>(
+    dir: &Path,
+    url: &str,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let path = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = path.split_once('/').map_or("", |(_, path)| path);
+    let name: String = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    let file = dir.join(format!("{name}.json"));
+    let body = fs::read_to_string(&file)
+        .map_err(|e| messages::fixture_missing(url, file.display(), e))?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The `-v` log line for a finished request.
+fn log_http(url: &str, status: reqwest::StatusCode, started: Instant, bytes: usize) {
+    if VERBOSITY.load(atomic::Ordering::Relaxed) >= 1 {
+        let ms = started.elapsed().as_millis();
+        eprintln!("{}", messages::http_request_log(url, status, ms, bytes).dimmed());
+    }
+}
+
+/// Details about a single package, as returned by the exact-name lookups.
+struct PackageInfo {
+    name: String,
+    version: String,
+    source: Source,
+    installed_size: Option<u64>,
+    depends: Vec<String>,
+    /// pacman's `name: reason` form.
+    optdepends: Vec<String>,
+    votes: Option<u64>,
+    /// The architectures an AUR package builds for; empty for official
+    /// packages, which the repos only serve for this machine.
+    arch: Vec<String>,
+}
+
+/// Looks up a package by exact name, trying the official repos first and
+/// the AUR second. `None` means neither has it.
+async fn fetch_package_info(
+    client: &Client,
+    package: &str,
+) -> Result<Option<PackageInfo>, Box<dyn std::error::Error>> {
+    let url = format!(
+        "{}/packages/search/json/?name={}",
+        official_base_url(),
+        urlencoding::encode(package)
+    );
+    let official: OfficialResponse = get_json(client, &url).await?;
+    if let Some(entry) = official.results.into_iter().next() {
+        let pkg: OfficialPackage = serde_json::from_value(entry)?;
+        return Ok(Some(PackageInfo {
+            name: package.to_string(),
+            version: pkg.version(),
+            source: "official",
+            installed_size: pkg.installed_size,
+            depends: pkg.depends,
+            optdepends: pkg.optdepends,
+            votes: None,
+            arch: Vec::new(),
+        }));
+    }
+
+    let url = format!(
+        "{}/rpc/?v=5&type=info&arg[]={}",
+        aur_base_url(),
+        urlencoding::encode(package)
+    );
+    let aur_data: AurResponse = get_json(client, &url).await?;
+    Ok(aur_data.results.into_iter().next().map(|pkg| PackageInfo {
+        name: pkg.name,
+        version: pkg.version,
+        source: "aur",
+        installed_size: None,
+        depends: pkg.depends,
+        optdepends: pkg.opt_depends,
+        votes: Some(pkg.num_votes),
+        arch: pkg.arch,
+    }))
+}
+
+/// Refuses an AUR package whose `arch` has neither this machine's arch nor
+/// `any`, unless `--ignore-arch` was passed. makepkg would refuse it as
+/// well, but only after the download and the helper's prompts.
+fn check_aur_arch(info: &PackageInfo, options: &InstallOptions) -> Result<(), String> {
+    let host = host_arch();
+    let builds_here = info.arch.is_empty() || info.arch.iter().any(|a| a == host || a == "any");
+    if info.source != "aur" || builds_here || options.ignore_arch {
+        return Ok(());
+    }
+    let arch = info.arch.join(", ");
+    Err(format!("{}", messages::arch_mismatch(&info.name, &arch, host).red()))
+}
+
+/// `check_aur_arch` for a package that hasn't been looked up yet. A failed
+/// lookup doesn't stop the install; the helper will report the problem.
+async fn check_arch(
+    client: &Client,
+    package: &str,
+    options: &InstallOptions,
+) -> Result<(), String> {
+    if options.ignore_arch {
+        return Ok(());
+    }
+    match fetch_package_info(client, package).await {
+        Ok(Some(info)) => check_aur_arch(&info, options),
+        _ => Ok(()),
+    }
+}
+
+async fn compare_packages(
+    client: &Client,
+    a: &str,
+    b: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (info_a, info_b) = tokio::join!(
+        fetch_package_info(client, a),
+        fetch_package_info(client, b)
+    );
+    let info_a = info_a?;
+    let info_b = info_b?;
+
+    for (name, info) in [(a, &info_a), (b, &info_b)] {
+        if info.is_none() {
+            eprintln!("{}", messages::package_not_found(name).yellow());
+        }
+    }
+    if info_a.is_none() && info_b.is_none() {
+        return Err(messages::neither_package_exists().into());
+    }
+
+    let values = |info: &Option<PackageInfo>| -> [String; 5] {
+        let Some(info) = info else {
+            return Default::default();
+        };
+        [
+            info.version.clone(),
+            info.source.to_string(),
+            info.installed_size.map(format_size).unwrap_or_default(),
+            info.depends.len().to_string(),
+            info.votes.map(|v| v.to_string()).unwrap_or_default(),
+        ]
+    };
+    let values_a = values(&info_a);
+    let values_b = values(&info_b);
+
+    let name_a = info_a.as_ref().map_or(a, |i| i.name.as_str());
+    let name_b = info_b.as_ref().map_or(b, |i| i.name.as_str());
+    println!(
+        "{:<16} {:<24} {}",
+        "",
+        name_a.green().bold(),
+        name_b.green().bold()
+    );
+    let labels = messages::compare_labels();
+    for ((label, value_a), value_b) in labels.iter().zip(values_a).zip(values_b) {
+        let value_a = if value_a.is_empty() { "-".to_string() } else { value_a };
+        let value_b = if value_b.is_empty() { "-".to_string() } else { value_b };
+        if value_a == value_b {
+            println!("{:<16} {:<24} {}", label.bold().white(), value_a, value_b);
+        } else {
+            println!(
+                "{:<16} {:<24} {}",
+                label.bold().white(),
+                value_a.yellow(),
+                value_b.yellow()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Formats a byte count the way pacman does, e.g. `25.31 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.2} {}", UNITS[unit])
+}
+
+/// Lists a freshly installed package's optional dependencies and installs
+/// the ones the user picks. Does nothing if the package declares none.
+async fn offer_optdepends(client: &Client, package: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let optdepends = match fetch_package_info(client, package).await {
+        Ok(info) => info.map(|info| info.optdepends).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", messages::optdepends_lookup_failed(e).yellow());
+            return Ok(());
+        }
+    };
+    if optdepends.is_empty() {
+        return Ok(());
+    }
+
+    println!("{}", messages::optdepends_header(package).bold().white());
+    for (i, dep) in optdepends.iter().enumerate() {
+        let (name, reason) = dep.split_once(':').unwrap_or((dep, ""));
+        println!(
+            "{}. {:<30} {}",
+            (i + 1).to_string().bold().white(),
+            name.trim().green(),
+            reason.trim()
+        );
+    }
+
+    print!("{}", messages::optdepends_prompt().bold().white());
+    io::stdout().flush()?;
+    let input = read_answer().unwrap_or_default();
+
+    let mut selected: Vec<&str> = Vec::new();
+    for choice in input.split([' ', ',']).filter_map(|c| c.trim().parse::<usize>().ok()) {
+        if let Some(dep) = choice.checked_sub(1).and_then(|i| optdepends.get(i)) {
+            let name = dep.split_once(':').map_or(dep.as_str(), |(name, _)| name).trim();
+            if !selected.contains(&name) {
+                selected.push(name);
+            }
+        }
+    }
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    let install_options = InstallOptions {
+        as_deps: true,
+        ..Default::default()
+    };
+    if install_batch(client, &selected, &install_options).await > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Installs several packages. Their details are looked up first, all at
+/// once within `max_concurrency`, to list what will be installed with the
+/// official packages' total size; then the official packages are installed
+/// with one pacman command and the AUR packages with one helper command, and
+/// each package gets its group's result. Packages found nowhere are skipped,
+/// and failures are reported without stopping. Returns how many were not
+/// installed.
+async fn install_batch(client: &Client, names: &[&str], options: &InstallOptions) -> usize {
+    let infos = fetch_batch_info(client, names).await;
+
+    println!("{}", messages::batch_header(names.len()).bold().white());
+    let mut total_size = 0;
+    for (name, info) in names.iter().zip(&infos) {
+        match info {
+            Some(info) => {
+                total_size += info.installed_size.unwrap_or(0);
+                let size = info.installed_size.map(format_size);
+                let line = messages::batch_line(name, &info.version, info.source, size.as_deref());
+                println!("  {line}");
+            }
+            None => println!("  {}", messages::batch_not_found(name).yellow()),
+        }
+    }
+    if total_size > 0 {
+        println!("{}", messages::batch_total_size(&format_size(total_size)));
+    }
+
+    // The failure reason for each package that failed, or is in a group
+    // that failed.
+    let mut failures: HashMap<&str, String> = HashMap::new();
+    for (name, info) in names.iter().zip(&infos) {
+        if let Some(Err(e)) = info.as_ref().map(|info| check_aur_arch(info, options)) {
+            eprintln!("{e}");
+            let reason = strip_ansi(&e).lines().next().unwrap_or_default().to_string();
+            failures.insert(name, reason);
+        }
+    }
+    for source in ["official", "aur"] {
+        let group: Vec<&str> = names
+            .iter()
+            .zip(&infos)
+            .filter(|(_, info)| info.as_ref().is_some_and(|info| info.source == source))
+            .map(|(name, _)| *name)
+            .filter(|name| !failures.contains_key(name))
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        if let Err(e) = install_packages(&group, source, options) {
+            eprintln!("{e}");
+            let reason = strip_ansi(&e).lines().next().unwrap_or_default().to_string();
+            failures.extend(group.into_iter().map(|name| (name, reason.clone())));
+        }
+    }
+    let outcomes: Vec<(&str, Option<String>)> = names
+        .iter()
+        .zip(&infos)
+        .map(|(name, info)| match info {
+            Some(_) => (*name, failures.remove(name)),
+            None => (*name, Some(messages::batch_reason_not_found())),
+        })
+        .collect();
+    if !outcomes.is_empty() {
+        print_batch_summary(&outcomes);
+    }
+    outcomes.iter().filter(|(_, reason)| reason.is_some()).count()
+}
+
+/// A ✓ or ✗ per package after a batch install, with the reason for each
+/// failure, so the result isn't lost in the tools' interleaved output.
+fn print_batch_summary(outcomes: &[(&str, Option<String>)]) {
+    println!("{}", messages::batch_summary_header().bold().white());
+    let rows: Vec<Vec<String>> = outcomes
+        .iter()
+        .map(|(name, reason)| {
+            let mark = if reason.is_some() { "✗" } else { "✓" };
+            let result = reason.clone().unwrap_or_else(messages::batch_installed);
+            vec![mark.to_string(), name.to_string(), result]
+        })
+        .collect();
+    let paint = |col: usize, text: &str| match (col, text) {
+        (0, "✓") => text.green().to_string(),
+        (0, _) => text.red().to_string(),
+        (1, _) => text.bold().to_string(),
+        _ => text.to_string(),
+    };
+
+    #[cfg(feature = "table")]
+    for line in table::render(&messages::batch_summary_headers(), &rows, Some(2), paint) {
+        println!("{line}");
+    }
+    #[cfg(not(feature = "table"))]
+    {
+        let width = rows.iter().map(|row| row[1].chars().count()).max().unwrap_or(0);
+        for row in &rows {
+            let padding = " ".repeat(width - row[1].chars().count());
+            println!("  {} {}{padding}  {}", paint(0, &row[0]), paint(1, &row[1]), row[2]);
+        }
+    }
+}
+
+/// `fetch_package_info` for every name concurrently, with a progress count
+/// on stderr when it is a terminal. Lookups that fail are warned about and
+/// come back as `None`, like packages that don't exist.
+async fn fetch_batch_info(client: &Client, names: &[&str]) -> Vec<Option<PackageInfo>> {
+    type Lookup<'a> =
+        Pin<Box<dyn Future<Output = Result<Option<PackageInfo>, Box<dyn std::error::Error>>> + 'a>>;
+    let started = Instant::now();
+    let done = AtomicUsize::new(0);
+    let show_progress = io::stderr().is_terminal();
+    let lookups = names
+        .iter()
+        .map(|name| {
+            let done = &done;
+            Box::pin(async move {
+                let info = fetch_package_info(client, name).await;
+                let done = done.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+                if show_progress {
+                    eprint!("\r{}", messages::batch_progress(done, names.len()).dimmed());
+                }
+                info
+            }) as Lookup
+        })
+        .collect();
+    let infos = sources::join_all(lookups).await;
+    if show_progress {
+        eprint!("\r\x1b[K");
+    }
+    if VERBOSITY.load(atomic::Ordering::Relaxed) >= 1 {
+        let ms = started.elapsed().as_millis();
+        eprintln!("{}", messages::summary_lookup_log(names.len(), ms).dimmed());
+    }
+
+    names
+        .iter()
+        .zip(infos)
+        .map(|(name, info)| {
+            info.unwrap_or_else(|e| {
+                eprintln!("{}", messages::lookup_failed(name, e).yellow());
+                None
+            })
+        })
+        .collect()
+}
+
+/// `archlink fav`. Adding a favorite twice or removing one that isn't there
+/// only prints a note.
+async fn favorites_command(
+    client: impl Fn() -> Client,
+    matches: &clap::ArgMatches,
+) -> io::Result<()> {
+    match matches.subcommand() {
+        Some(("add", sub_m)) => {
+            let package = sub_m.get_one::<String>("package").expect("package is required");
+            if favorites::add(package)? {
+                println!("{}", messages::favorite_added(package).green());
+            } else {
+                println!("{}", messages::favorite_exists(package).yellow());
+            }
+        }
+        Some(("remove", sub_m)) => {
+            let package = sub_m.get_one::<String>("package").expect("package is required");
+            if favorites::remove(package)? {
+                println!("{}", messages::favorite_removed(package).green());
+            } else {
+                println!("{}", messages::not_a_favorite(package).yellow());
+            }
+        }
+        Some(("list", _)) => {
+            let favorites = favorites::read()?;
+            if favorites.is_empty() {
+                println!("{}", messages::no_favorites());
+            }
+            for favorite in favorites {
+                println!("{}", favorite.green());
+            }
+        }
+        Some(("install", _)) => {
+            let favorites = favorites::read()?;
+            if favorites.is_empty() {
+                println!("{}", messages::no_favorites());
+                return Ok(());
+            }
+            let names: Vec<&str> = favorites.iter().map(String::as_str).collect();
+            let failed = install_batch(&client(), &names, &InstallOptions::default()).await;
+            if failed > 0 {
+                eprintln!("{}", messages::favorites_failed(failed, names.len()).red());
+                std::process::exit(1);
+            }
         }
         _ => unreachable!(),
     }
+    Ok(())
+}
+
+/// URLs for inspecting a package by hand: the web page for official
+/// packages, and the snapshot tarball and git clone URL for AUR packages.
+fn package_urls(pkg: &Package) -> Vec<String> {
+    if pkg.source == "flatpak" {
+        return vec![format!("https://flathub.org/apps/{}", pkg.name)];
+    }
+    if pkg.source == "aur" {
+        return vec![
+            format!(
+                "https://aur.archlinux.org/cgit/aur.git/snapshot/{}.tar.gz",
+                pkg.name
+            ),
+            format!("https://aur.archlinux.org/{}.git", pkg.name),
+        ];
+    }
+    match (&pkg.repo, &pkg.arch) {
+        (Some(repo), Some(arch)) => vec![format!(
+            "https://archlinux.org/packages/{repo}/{arch}/{}/",
+            pkg.name
+        )],
+        _ => vec![format!(
+            "https://archlinux.org/packages/?q={}",
+            urlencoding::encode(&pkg.name)
+        )],
+    }
+}
+
+fn rank_results(candidates: Vec<Package>, query: &str, options: &SearchOptions) -> RankedResults {
+    let candidates = if options.merge_arch { merge_arches(candidates) } else { candidates };
+    let count = |source: &str| candidates.iter().filter(|pkg| pkg.source == source).count();
+    let official_total = count("official");
+    let aur_total = count("aur");
+    let flatpak_total = count("flatpak");
+    // A limit of 0, from the CLI, environment or config alike, means none.
+    let max_results = match options.max_results {
+        0 => usize::MAX,
+        max_results => max_results,
+    };
+
+    if let Some(seed) = options.random_seed {
+        let mut packages = candidates;
+        shuffle(&mut packages, seed);
+        packages.truncate(max_results);
+        return RankedResults {
+            packages,
+            official_total,
+            aur_total,
+            flatpak_total,
+        };
+    }
+
+    let query_words: Vec<&str> = query.split_whitespace().collect();
+    let mut scored: Vec<(u32, usize, Package)> = candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, pkg)| {
+            let score = score_package(&pkg, query, &query_words, options);
+            (score, i, pkg)
+        })
+        .collect();
+
+    // Highest score first, ties by name and then source, so the order never
+    // depends on which source answered first. Only the top `max_results`
+    // entries are ever sorted. The other sort orders go first, with packages
+    // missing a date or size always last.
+    let by_rank = |a: &(u32, usize, Package), b: &(u32, usize, Package)| {
+        let by_sort = match options.sort {
+            SortBy::Relevance => Ordering::Equal,
+            SortBy::Newest => b.2.last_updated.cmp(&a.2.last_updated),
+            SortBy::Largest => b.2.installed_size.cmp(&a.2.installed_size),
+            SortBy::ReverseDeps => b.2.reverse_deps.cmp(&a.2.reverse_deps),
+            SortBy::Name => a.2.name.cmp(&b.2.name),
+            SortBy::Votes => b.2.votes.cmp(&a.2.votes),
+            SortBy::Smallest => match (a.2.installed_size, b.2.installed_size) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => b.is_none().cmp(&a.is_none()),
+            },
+        };
+        by_sort
+            .then(b.0.cmp(&a.0))
+            .then_with(|| a.2.name.cmp(&b.2.name))
+            .then_with(|| source_order(a.2.source).cmp(&source_order(b.2.source)))
+            .then(a.1.cmp(&b.1))
+    };
+    if scored.len() > max_results {
+        scored.select_nth_unstable_by(max_results - 1, by_rank);
+        scored.truncate(max_results);
+    }
+    scored.sort_unstable_by(by_rank);
+
+    RankedResults {
+        packages: scored.into_iter().map(|(_, _, pkg)| pkg).collect(),
+        official_total,
+        aur_total,
+        flatpak_total,
+    }
+}
+
+/// Folds official packages that share a name into the first of them, which
+/// lists every arch in `arches`. Other sources are left alone.
+fn merge_arches(candidates: Vec<Package>) -> Vec<Package> {
+    let mut merged: Vec<Package> = Vec::with_capacity(candidates.len());
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for mut pkg in candidates {
+        if pkg.source != "official" {
+            merged.push(pkg);
+            continue;
+        }
+        let arch = pkg.arch.clone().unwrap_or_default();
+        match seen.get(&pkg.name) {
+            Some(&i) => {
+                if !merged[i].arches.contains(&arch) {
+                    merged[i].arches.push(arch);
+                }
+            }
+            None => {
+                seen.insert(pkg.name.clone(), merged.len());
+                pkg.arches = vec![arch];
+                merged.push(pkg);
+            }
+        }
+    }
+    merged
+}
+
+/// Where a source sorts among equally ranked packages.
+fn source_order(source: &str) -> u8 {
+    match source {
+        "official" => 0,
+        "aur" => 1,
+        _ => 2,
+    }
+}
+
+/// Fisher-Yates shuffle driven by splitmix64, so a seed always gives the same
+/// order.
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// A seed for `--random` without `--seed`.
+fn time_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    nanos ^ u64::from(std::process::id())
+}
+
+/// Whether `pkg` is maintained by someone in `trusted_maintainers`, ignoring
+/// case.
+fn is_trusted(pkg: &Package, trusted_maintainers: &[String]) -> bool {
+    pkg.maintainer.as_deref().is_some_and(|maintainer| {
+        trusted_maintainers
+            .iter()
+            .any(|trusted| trusted.eq_ignore_ascii_case(maintainer))
+    })
+}
+
+/// Whether `pkg` survives `--min-votes`. Only AUR packages have votes, so
+/// packages from other sources always do.
+fn meets_min_votes(pkg: &Package, min_votes: u64) -> bool {
+    pkg.source != "aur" || pkg.votes.unwrap_or(0) >= min_votes
+}
+
+/// Whether every word of `query` appears in the package's name or
+/// description.
+fn matches_every_word(pkg: &Package, query: &str, case_sensitive: bool) -> bool {
+    let fold = |text: &str| if case_sensitive { text.to_string() } else { text.to_lowercase() };
+    let haystack = format!("{} {}", fold(&pkg.name), fold(&pkg.description));
+    query.split_whitespace().all(|word| haystack.contains(&fold(word)))
+}
+
+fn score_package(pkg: &Package, query: &str, query_words: &[&str], options: &SearchOptions) -> u32 {
+    score_breakdown(pkg, query, query_words, options).score
+}
+
+/// Weights from the `[ranking]` config table. A package's score is 1000
+/// plus each weight times its signal, so a weight of 0 turns a signal off.
+/// The defaults give the built-in ranking.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+struct RankingWeights {
+    /// Points lost per unit of distance between the name and the query.
+    name_distance: f64,
+    /// Points per query word found in the description.
+    desc_match: f64,
+    /// Points for being in the official repos.
+    source_official: f64,
+    /// Points per power of ten of AUR votes, so 9 votes count 1 and 99 count 2.
+    aur_votes: f64,
+    /// Points for a package updated today, falling to none a year later.
+    recency: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> RankingWeights {
+        RankingWeights {
+            name_distance: 1.0,
+            desc_match: 50.0,
+            source_official: 0.0,
+            aur_votes: 0.0,
+            recency: 0.0,
+        }
+    }
+}
+
+/// The parts that make up a package's ranking score, each already weighted
+/// except the raw `name_distance`.
+#[derive(Serialize)]
+struct ScoreBreakdown {
+    name_distance: u32,
+    description_bonus: f64,
+    source_bonus: f64,
+    votes_bonus: f64,
+    recency_bonus: f64,
+    /// `prefer_margin`, for packages from the `--prefer` source.
+    prefer_bonus: f64,
+    /// `TRUSTED_BOOST`, for packages from a `trusted_maintainers` entry.
+    trusted_bonus: f64,
+    score: u32,
+}
+
+fn score_breakdown(
+    pkg: &Package,
+    query: &str,
+    query_words: &[&str],
+    options: &SearchOptions,
+) -> ScoreBreakdown {
+    let (name, query) = if options.case_sensitive {
+        (pkg.name.clone(), query.to_string())
+    } else {
+        (pkg.name.to_lowercase(), query.to_lowercase())
+    };
+    let name_distance = match options.match_algorithm {
+        MatchAlgorithm::Levenshtein => levenshtein(&name, &query) as u32,
+        #[cfg(feature = "fuzzy")]
+        MatchAlgorithm::Fuzzy => fuzzy_distance(&name, &query),
+        #[cfg(not(feature = "fuzzy"))]
+        MatchAlgorithm::Fuzzy => levenshtein(&name, &query) as u32,
+    };
+
+    let desc_lower = pkg.description.to_lowercase();
+    let desc_matches = query_words
+        .iter()
+        .filter(|word| desc_lower.contains(&word.to_lowercase()))
+        .count();
+    let official = if pkg.source == "official" { 1.0 } else { 0.0 };
+    let votes = pkg.votes.map_or(0.0, |votes| (votes as f64 + 1.0).log10());
+    let recency = pkg.last_updated.map_or(0.0, |updated| {
+        let days = (dates::now() - updated) as f64 / 86_400.0;
+        (1.0 - days / 365.0).clamp(0.0, 1.0)
+    });
+
+    let weights = &options.ranking;
+    let description_bonus = weights.desc_match * desc_matches as f64;
+    let source_bonus = weights.source_official * official;
+    let votes_bonus = weights.aur_votes * votes;
+    let recency_bonus = weights.recency * recency;
+    let preferred = options.prefer.as_deref() == Some(pkg.source);
+    let prefer_bonus = if preferred { f64::from(options.prefer_margin) } else { 0.0 };
+    let trusted_bonus = if pkg.trusted { f64::from(TRUSTED_BOOST) } else { 0.0 };
+    let score = 1000.0 - weights.name_distance * f64::from(name_distance)
+        + description_bonus
+        + source_bonus
+        + votes_bonus
+        + recency_bonus
+        + prefer_bonus
+        + trusted_bonus;
+
+    ScoreBreakdown {
+        name_distance,
+        description_bonus,
+        source_bonus,
+        votes_bonus,
+        recency_bonus,
+        prefer_bonus,
+        trusted_bonus,
+        score: score.round().max(0.0) as u32,
+    }
+}
+
+/// A subsequence match cost: 0 when the query is the whole name, growing
+/// with the characters skipped before, between and after the matched ones.
+/// Skips that land on a word start after `-`, `_` or `.` cost less, so `ff`
+/// finds `firefox` and `gst` finds `gst-plugins-base`. A query that is not a
+/// subsequence of the name always ranks below one that is.
+#[cfg(feature = "fuzzy")]
+fn fuzzy_distance(name: &str, query: &str) -> u32 {
+    let name: Vec<char> = name.chars().collect();
+    let query: Vec<char> = query.chars().collect();
+    let Some((&first, rest)) = query.split_first() else {
+        return name.len() as u32;
+    };
+    let word_start = |i: usize| i == 0 || matches!(name[i - 1], '-' | '_' | '.');
+
+    let mut best: Option<usize> = None;
+    for start in (0..name.len()).filter(|&i| name[i] == first) {
+        let mut cost = if word_start(start) { start / 2 } else { start };
+        let mut pos = start;
+        let mut matched = true;
+        for &c in rest {
+            let Some(offset) = name[pos + 1..].iter().position(|&n| n == c) else {
+                matched = false;
+                break;
+            };
+            let next = pos + 1 + offset;
+            cost += offset * if word_start(next) { 1 } else { 2 };
+            pos = next;
+        }
+        // Starting later leaves less of the name, so nothing later matches.
+        if !matched {
+            break;
+        }
+        cost += name.len() - pos - 1;
+        best = Some(best.map_or(cost, |b| b.min(cost)));
+    }
+
+    match best {
+        Some(cost) => cost.min(99) as u32,
+        None => {
+            let name: String = name.into_iter().collect();
+            let query: String = query.into_iter().collect();
+            100 + levenshtein(&name, &query).min(800) as u32
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RankDebugRow<'a> {
+    name: &'a str,
+    source: &'a str,
+    #[serde(flatten)]
+    breakdown: ScoreBreakdown,
+}
+
+/// Writes the score breakdown of every candidate, before truncation, to
+/// `path`. A `.csv` extension selects CSV; anything else gets JSON.
+fn export_rank_debug(
+    path: &Path,
+    candidates: &[&Package],
+    query: &str,
+    options: &SearchOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let query_words: Vec<&str> = query.split_whitespace().collect();
+    let mut rows: Vec<RankDebugRow> = candidates
+        .iter()
+        .map(|pkg| RankDebugRow {
+            name: &pkg.name,
+            source: pkg.source,
+            breakdown: score_breakdown(pkg, query, &query_words, options),
+        })
+        .collect();
+    rows.sort_by_key(|row| Reverse(row.breakdown.score));
+
+    let contents = if path.extension().is_some_and(|ext| ext == "csv") {
+        let mut csv = String::from(
+            "name,source,name_distance,description_bonus,source_bonus,votes_bonus,\
+             recency_bonus,prefer_bonus,trusted_bonus,score\r\n",
+        );
+        for row in &rows {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}\r\n",
+                csv_field(row.name),
+                csv_field(row.source),
+                row.breakdown.name_distance,
+                row.breakdown.description_bonus,
+                row.breakdown.source_bonus,
+                row.breakdown.votes_bonus,
+                row.breakdown.recency_bonus,
+                row.breakdown.prefer_bonus,
+                row.breakdown.trusted_bonus,
+                row.breakdown.score
+            ));
+        }
+        csv
+    } else {
+        serde_json::to_string_pretty(&rows)? + "\n"
+    };
+    write_atomic(path, contents)?;
+    Ok(())
+}
+
+/// `text` without the escape codes `colored` adds.
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the sequence, e.g. `\x1b[31m`.
+            chars.by_ref().find(char::is_ascii_alphabetic);
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Asks a yes/no question. `--yes` answers yes without asking, and a
+/// non-interactive stdin falls back to `default`.
+fn confirm(prompt: &str, default: bool) -> io::Result<bool> {
+    if ASSUME_YES.load(atomic::Ordering::Relaxed) {
+        return Ok(true);
+    }
+    ask(prompt, default)
+}
+
+/// Like `confirm`, but always asks, even under `--yes`, for the few
+/// questions that shouldn't be answered in advance.
+fn ask(prompt: &str, default: bool) -> io::Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(default);
+    }
+
+    let hint = messages::confirm_hint(default);
+    print!("{}", format!("{prompt} ({hint})? ").bold().white());
+    io::stdout().flush()?;
+    let Some(answer) = read_answer() else {
+        return Ok(false);
+    };
+    let answer = answer.trim();
+    if answer.is_empty() {
+        Ok(default)
+    } else {
+        Ok(messages::is_yes(answer))
+    }
+}
+
+/// Reads one line of input for a prompt. A read error, such as input that
+/// isn't valid UTF-8, gives `None` so callers can treat it as a cancel.
+fn read_answer() -> Option<String> {
+    let mut input = String::new();
+    io::stdin().lock().read_line(&mut input).ok()?;
+    Some(input)
+}
+
+fn install_package(package: &str, source: &str, options: &InstallOptions) -> Result<(), String> {
+    if source == "flatpak" {
+        return install_flatpak(package);
+    }
+    install_packages(&[package], source, options)
+}
+
+/// Installs `packages`, all from `source`, with a single command per tool,
+/// so pacman resolves them together and asks for root once. Packages that
+/// are already installed are left out. The tools are tried in
+/// `install_order` until one installs the whole group.
+fn install_packages(
+    packages: &[&str],
+    source: &str,
+    options: &InstallOptions,
+) -> Result<(), String> {
+    ensure_pacman()?;
+    let mut pending = Vec::new();
+    for &package in packages {
+        match installed_version(package) {
+            Some(version) if !options.reinstall => {
+                println!("{}", messages::already_installed(package, &version).yellow());
+            }
+            _ => pending.push(package),
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let shown = pending.join(" ");
+    let mut attempted = Vec::new();
+    let extra_args = options.extra_args();
+
+    // pacman only knows the official repos, and helpers that aren't
+    // installed are skipped.
+    for &tool in install_order() {
+        let name = tool.name();
+        let status = if tool == InstallTool::Pacman {
+            if source != "official" && source != "unknown" {
+                continue;
+            }
+            attempted.push(name);
+            println!(
+                "{}",
+                messages::trying_command(&root_pacman(&format!("-S {shown}")))
+                    .bold()
+                    .white()
+            );
+            pacman_as_root()?
+                .arg("-S")
+                .args(&pending)
+                .arg("--noconfirm")
+                .args(&extra_args)
+                .status()
+        } else {
+            if !is_command_in_path(name) {
+                continue;
+            }
+            attempted.push(name);
+            println!(
+                "{}",
+                messages::trying_command(&format!("{name} -S {shown}")).bold().white()
+            );
+            // makepkg refuses a PKGBUILD whose `arch` leaves out this
+            // machine unless told to ignore it.
+            let mut command = SysCommand::new(name);
+            command.arg("-S").args(&extra_args);
+            if options.ignore_arch {
+                command.args(["--mflags", "--ignorearch"]);
+            }
+            command.args(&pending).status()
+        };
+        if status.map_err(|e| messages::run_failed(name, e))?.success() {
+            let source = if tool == InstallTool::Pacman { "official" } else { "aur" };
+            for package in &pending {
+                installed(package, name, source);
+            }
+            return Ok(());
+        }
+    }
+
+    Err(format!("{}", messages::install_failed(&shown, &attempted).red()))
+}
+
+/// Installs exactly `version` of an official package from pacman's package
+/// cache with `pacman -U`. Repos only serve the latest version, so a version
+/// that was never downloaded here can't be installed, and AUR packages
+/// can't be pinned at all; both are errors rather than a silent install of
+/// whatever is current.
+fn install_pinned(package: &str, version: &str, options: &InstallOptions) -> Result<(), String> {
+    ensure_pacman()?;
+    if !options.reinstall && installed_version(package).as_deref() == Some(version) {
+        println!("{}", messages::already_installed(package, version).yellow());
+        return Ok(());
+    }
+    if !in_official_repos(package) {
+        return Err(format!("{}", messages::aur_not_pinnable(package, version).red()));
+    }
+    let Some(file) = cached_package_file(package, version) else {
+        return Err(format!("{}", messages::pinned_not_cached(package, version).red()));
+    };
+
+    // The file name ends in the arch it was built for. pacman would refuse a
+    // mismatch too, but only after asking for root.
+    let arch = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(&format!("{package}-{version}-")))
+        .and_then(|rest| rest.split(".pkg.tar").next())
+        .unwrap_or("any")
+        .to_string();
+    let mismatch = arch != "any" && arch != host_arch();
+    if mismatch && !options.ignore_arch {
+        return Err(format!("{}", messages::arch_mismatch(package, &arch, host_arch()).red()));
+    }
+
+    let file = file.display().to_string();
+    println!(
+        "{}",
+        messages::trying_command(&root_pacman(&format!("-U {file}")))
+            .bold()
+            .white()
+    );
+    let mut command = pacman_as_root()?;
+    command.args(["-U", &file, "--noconfirm"]).args(options.extra_args());
+    if mismatch {
+        command.args(["--arch", &arch]);
+    }
+    let status = command
+        .status()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    if status.success() {
+        installed(package, "pacman", "official");
+        Ok(())
+    } else {
+        Err(format!("{}", messages::install_failed(package, &["pacman"]).red()))
+    }
+}
+
+/// Downloads an official package, and any dependencies that aren't
+/// installed, into pacman's cache with `pacman -Sw`, for installing later
+/// without a connection. AUR packages only exist as build scripts, so there
+/// is nothing to download; the error points at `--clone-only` instead.
+fn download_package(package: &str) -> Result<(), String> {
+    ensure_pacman()?;
+    if !in_official_repos(package) {
+        return Err(format!("{}", messages::aur_not_downloadable(package).red()));
+    }
+    println!(
+        "{}",
+        messages::trying_command(&root_pacman(&format!("-Sw {package}")))
+            .bold()
+            .white()
+    );
+    let status = pacman_as_root()?
+        .args(["-Sw", package, "--noconfirm"])
+        .status()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    if !status.success() {
+        return Err(format!("{}", messages::download_failed(package).red()));
+    }
+
+    // `-dd` leaves out the dependencies, so only the package's own file is
+    // printed.
+    let file = SysCommand::new("pacman")
+        .args(["-Sp", "-dd", "--print-format", "%f", package])
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|stdout| stdout.lines().last().map(str::to_string));
+    let location = match file {
+        Some(file) => Path::new(PACMAN_CACHE_DIR).join(file),
+        None => PathBuf::from(PACMAN_CACHE_DIR),
+    };
+    println!("{}", messages::downloaded_to(package, location.display()).green());
+    Ok(())
+}
+
+/// This machine's architecture as pacman names it, e.g. `x86_64` or
+/// `aarch64`.
+fn host_arch() -> &'static str {
+    match env::consts::ARCH {
+        "x86" => "i686",
+        "arm" => "armv7h",
+        arch => arch,
+    }
+}
+
+fn in_official_repos(package: &str) -> bool {
+    SysCommand::new("pacman")
+        .args(["-Si", package])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// `<name>-<version>-<arch>.pkg.tar.*` in pacman's cache, skipping signatures.
+fn cached_package_file(package: &str, version: &str) -> Option<PathBuf> {
+    let prefix = format!("{package}-{version}-");
+    fs::read_dir(PACMAN_CACHE_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                return false;
+            };
+            name.strip_prefix(&prefix).is_some_and(|rest| {
+                // What follows the version is only the arch.
+                let arch = rest.split(".pkg.tar").next().unwrap_or(rest);
+                rest.contains(".pkg.tar") && !arch.contains('-') && !name.ends_with(".sig")
+            })
+        })
+}
+
+/// Reports a successful install and records it in archlink's install log.
+fn installed(package: &str, tool: &str, source: &str) {
+    println!("{}", messages::installed_with(package, tool).green());
+    let version = match source {
+        "flatpak" => None,
+        _ => installed_version(package),
+    };
+    install_log::record(package, version.as_deref().unwrap_or("-"), source);
+}
+
+/// Flatpak apps install per remote rather than through pacman, so the
+/// pacman-only `InstallOptions` don't apply.
+fn install_flatpak(app: &str) -> Result<(), String> {
+    println!(
+        "{}",
+        messages::trying_command(&format!("flatpak install {app}"))
+            .bold()
+            .white()
+    );
+    let status = SysCommand::new("flatpak")
+        .args(["install", "-y", app])
+        .status()
+        .map_err(|e| messages::run_failed("flatpak", e))?;
+    if status.success() {
+        installed(app, "flatpak", "flatpak");
+        Ok(())
+    } else {
+        Err(format!("{}", messages::install_failed(app, &["flatpak"]).red()))
+    }
+}
+
+/// Clones `package`'s AUR repo into `dir` for building by hand, or offers to
+/// pull if it was cloned before.
+fn clone_aur(package: &str, dir: &Path) -> Result<(), String> {
+    if !is_command_in_path("git") {
+        return Err(format!("{}", messages::git_missing().red()));
+    }
+    let target = dir.join(package);
+    let status = if target.join(".git").is_dir() {
+        let prompt = messages::aur_pull_confirm(target.display());
+        if !confirm(&prompt, true).map_err(|e| e.to_string())? {
+            return Ok(());
+        }
+        println!(
+            "{}",
+            messages::running_command(&format!("git -C {} pull", target.display()))
+                .bold()
+                .white()
+        );
+        SysCommand::new("git")
+            .arg("-C")
+            .arg(&target)
+            .args(["pull", "--ff-only"])
+            .status()
+    } else {
+        fs::create_dir_all(dir).map_err(|e| messages::error(e).red().to_string())?;
+        let url = format!("https://aur.archlinux.org/{package}.git");
+        println!(
+            "{}",
+            messages::running_command(&format!("git clone {url}")).bold().white()
+        );
+        SysCommand::new("git").arg("clone").arg(&url).arg(&target).status()
+    }
+    .map_err(|e| messages::run_failed("git", e))?;
 
-    Ok(())
+    if status.success() {
+        println!("{}", messages::aur_cloned(target.display()).green());
+        Ok(())
+    } else {
+        Err(format!("{}", messages::aur_clone_failed(package).red()))
+    }
 }
 
-async fn search_packages(
-    client: &Client,
-    query: &str,
-    max_results: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
-    println!("{}", "Searching official repos and AUR...".bold().white());
+fn upgrade_system(ignored: &[String], allow_downgrade: bool) -> Result<(), String> {
+    ensure_pacman()?;
+    let ignore_list = ignored.join(",");
+    let mut ignore_args = Vec::new();
+    if !ignored.is_empty() {
+        println!("{}", messages::holding_back(ignored).yellow());
+        ignore_args.push("--ignore");
+        ignore_args.push(ignore_list.as_str());
+    }
+
+    // archlink never syncs the databases without upgrading (a bare -Sy):
+    // that leaves packages built against the new repos installable next to
+    // old libraries, the partial upgrade Arch does not support. Pending
+    // upgrades in the current databases mean something else already did,
+    // and the -Syu below completes it.
+    let pending = pending_upgrades();
+    if !pending.is_empty() {
+        println!("{}", messages::partial_sync_detected(pending.len()).yellow());
+    }
+    // A stale keyring fails on packages signed by newer packager keys, so it
+    // goes first.
+    let keyring = "archlinux-keyring".to_string();
+    if pending.contains(&keyring) && !ignored.contains(&keyring) {
+        println!("{}", messages::keyring_outdated().yellow());
+        println!(
+            "{}",
+            messages::running_command(&root_pacman("-S --needed archlinux-keyring"))
+                .bold()
+                .white()
+        );
+        let status = pacman_as_root()?
+            .args(["-S", "--needed", "--noconfirm", "archlinux-keyring"])
+            .status()
+            .map_err(|e| messages::run_failed("pacman", e))?;
+        if !status.success() {
+            return Err(format!("{}", messages::keyring_upgrade_failed().red()));
+        }
+    }
 
-    let (official_res, aur_res) = tokio::join!(
-        search_arch_website(client, query),
-        search_aur(client, query)
+    println!(
+        "{}",
+        messages::running_command(&root_pacman("-Syu")).bold().white()
     );
+    let status = pacman_as_root()?
+        .args(["-Syu"])
+        .args(&ignore_args)
+        .status()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    if !status.success() {
+        return Err(format!("{}", messages::pacman_upgrade_failed().red()));
+    }
 
-    let official_results = match official_res {
-        Ok(packages) => packages,
-        Err(e) => {
-            eprintln!(
+    // -Syu leaves packages that are newer than the repos alone, with only a
+    // warning in its output. With the databases now synced, look for them.
+    let downgrades = pending_downgrades(&ignore_args);
+    if !downgrades.is_empty() {
+        println!("{}", messages::downgrades_pending(&downgrades).yellow().bold());
+        if !allow_downgrade {
+            println!("{}", messages::downgrade_hint());
+        } else if confirm(&messages::downgrade_confirm(downgrades.len()), false)
+            .map_err(|e| e.to_string())?
+        {
+            println!(
                 "{}",
-                format!("Warning: Official repo search failed: {e}").yellow()
+                messages::running_command(&root_pacman("-Suu")).bold().white()
             );
-            Vec::new()
+            let status = pacman_as_root()?
+                .args(["-Suu"])
+                .args(&ignore_args)
+                .status()
+                .map_err(|e| messages::run_failed("pacman", e))?;
+            if !status.success() {
+                return Err(format!("{}", messages::downgrade_failed().red()));
+            }
+        } else {
+            println!("{}", messages::downgrade_skipped().yellow());
         }
-    };
+    }
 
-    let aur_results = match aur_res {
-        Ok(packages) => packages,
-        Err(e) => {
-            eprintln!("{}", format!("Warning: AUR search failed: {e}").yellow());
-            Vec::new()
+    for helper in ["yay", "paru"] {
+        if is_command_in_path(helper) {
+            println!(
+                "{}",
+                messages::running_aur_upgrade(helper).bold().white()
+            );
+            let status = SysCommand::new(helper)
+                .arg("-Sua")
+                .args(&ignore_args)
+                .status()
+                .map_err(|e| messages::run_failed(helper, e))?;
+            if !status.success() {
+                return Err(format!("{}", messages::aur_upgrade_failed(helper).red()));
+            }
+            println!("{}", messages::upgrade_complete().green());
+            return Ok(());
         }
+    }
+
+    println!("{}", messages::no_aur_helper_for_upgrade().yellow());
+    println!("{}", messages::upgrade_complete().green());
+    Ok(())
+}
+
+/// Packages with a newer version in the local sync databases, from
+/// `pacman -Qu`. Empty if there are none or pacman fails.
+fn pending_upgrades() -> Vec<String> {
+    SysCommand::new("pacman")
+        .arg("-Qu")
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.split_whitespace().next().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Installed packages whose sync database version is older than the
+/// installed one, e.g. after a repo rollback, as `(name, installed, repo)`.
+/// `pacman -Suup` prints what `-Suu` would install without doing it, and
+/// `vercmp` tells the downgrades apart from the upgrades.
+fn pending_downgrades(ignore_args: &[&str]) -> Vec<(String, String, String)> {
+    let Ok(output) = SysCommand::new("pacman")
+        .args(["-Suup", "--print-format", "%n %v"])
+        .args(ignore_args)
+        .output()
+    else {
+        return Vec::new();
     };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (name, repo) = line.split_once(' ')?;
+            let installed = installed_version(name)?;
+            let newer = SysCommand::new("vercmp")
+                .args([&installed, repo])
+                .output()
+                .ok()
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .and_then(|order| order.trim().parse::<i32>().ok())
+                .is_some_and(|order| order > 0);
+            newer.then(|| (name.to_string(), installed, repo.to_string()))
+        })
+        .collect()
+}
 
-    let all_results = rank_results(official_results, aur_results, query, max_results);
+/// Runs a `pacman -F` query. If pacman reports that the files database was
+/// never synced, syncs it with `pacman -Fy` and retries once.
+fn query_files(args: &[&str], refresh: bool) -> Result<(), String> {
+    ensure_pacman()?;
+    if refresh {
+        refresh_files_db()?;
+    }
 
-    if all_results.is_empty() {
-        println!(
-            "{}",
-            format!(
-                "No packages found for '{query}'. Try refining your query."
-            )
-            .yellow()
-        );
-        return Ok(());
+    let run = || {
+        SysCommand::new("pacman")
+            .args(args)
+            .output()
+            .map_err(|e| messages::run_failed("pacman", e))
+    };
+    let mut output = run()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // pacman: "database file for 'core' does not exist (use '-Fy' to download)"
+    if stderr.contains("-Fy") && !FILES_DB_REFRESHED.load(atomic::Ordering::Relaxed) {
+        println!("{}", messages::files_db_missing().yellow());
+        refresh_files_db()?;
+        output = run()?;
     }
 
-    println!("{}", format!("Suggestions for '{query}':").bold().white());
-    for (i, pkg) in all_results.iter().enumerate() {
-        println!(
-            "{}. {:<30} {:<15} - {} [{}]",
-            (i + 1).to_string().bold().white(),
-            pkg.name.green(),
-            pkg.version.blue(),
-            pkg.description,
-            pkg.source.cyan()
-        );
+    io::stdout().write_all(&output.stdout).map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim_end().red().to_string())
     }
+}
 
-    print!(
+fn refresh_files_db() -> Result<(), String> {
+    if FILES_DB_REFRESHED.swap(true, atomic::Ordering::Relaxed) {
+        return Ok(());
+    }
+    println!(
         "{}",
-        "Enter the number of the package to install (0 to exit): "
-            .bold()
-            .white()
+        messages::running_command(&root_pacman("-Fy")).bold().white()
     );
-    io::stdout().flush()?;
-    let mut input = String::new();
-    io::stdin().lock().read_line(&mut input)?;
-    let choice = input.trim().parse::<usize>().unwrap_or(0);
+    let status = pacman_as_root()?
+        .args(["-Fy"])
+        .status()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{}", messages::files_db_refresh_failed().red()))
+    }
+}
 
-    if choice > 0 && choice <= all_results.len() {
-        let selected_package = &all_results[choice - 1];
-        print!(
-            "{}",
-            format!("Install '{}' (y/N)? ", selected_package.name)
-                .bold()
-                .white()
-        );
-        io::stdout().flush()?;
-        let mut confirm = String::new();
-        io::stdin().lock().read_line(&mut confirm)?;
-        if confirm.trim().to_lowercase().starts_with('y') {
-            if let Err(e) = install_package(&selected_package.name, selected_package.source) {
-                eprintln!("{e}");
-                std::process::exit(1);
-            }
-        } else {
-            println!("{}", "Installation cancelled.".yellow());
+/// Writes every explicitly installed package to `path`, official packages
+/// and foreign (AUR) packages under separate headings, each with its
+/// installed version as a comment.
+fn export_manifest(path: &Path) -> Result<(), String> {
+    ensure_pacman()?;
+    let query = |flags: &str| -> Result<Vec<(String, String)>, String> {
+        let output = SysCommand::new("pacman")
+            .arg(flags)
+            .output()
+            .map_err(|e| messages::run_failed("pacman", e))?;
+        // pacman exits 1 with no output when nothing matches.
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect())
+    };
+    let explicit = query("-Qe")?;
+    let foreign: HashSet<String> = query("-Qem")?.into_iter().map(|(name, _)| name).collect();
+    let (aur, official): (Vec<_>, Vec<_>) =
+        explicit.iter().partition(|(name, _)| foreign.contains(name));
+
+    let mut manifest = format!(
+        "# archlink manifest, generated {}\n",
+        dates::format_timestamp(dates::now())
+    );
+    for (heading, packages) in [("[official]", &official), ("[aur]", &aur)] {
+        manifest.push_str(&format!("\n{heading}\n"));
+        for (name, version) in packages {
+            manifest.push_str(&format!("{name} # {version}\n"));
         }
-    } else if choice != 0 {
-        println!("{}", "Invalid selection. Exiting.".yellow());
     }
-
+    write_atomic(path, manifest).map_err(|e| messages::error(e).red().to_string())?;
+    println!(
+        "{}",
+        messages::manifest_written(official.len(), aur.len(), path.display()).green()
+    );
     Ok(())
 }
 
-async fn search_arch_website(client: &Client, query: &str) -> Result<Vec<Package>, reqwest::Error> {
-    let url = format!(
-        "https://archlinux.org/packages/search/json/?q={}",
-        urlencoding::encode(query)
-    );
-    let response = client.get(&url).send().await?;
-    let json: serde_json::Value = response.json().await?;
-
-    let mut packages = Vec::new();
-    if let Some(results) = json.get("results").and_then(|r| r.as_array()) {
-        for pkg in results {
-            let name = pkg
-                .get("pkgname")
-                .and_then(|n| n.as_str())
-                .unwrap_or("unknown")
-                .to_string();
-            let version = format!(
-                "{}-{}",
-                pkg.get("pkgver").and_then(|v| v.as_str()).unwrap_or(""),
-                pkg.get("pkgrel").and_then(|r| r.as_str()).unwrap_or("")
-            );
-            let description = pkg
-                .get("pkgdesc")
-                .and_then(|d| d.as_str())
-                .unwrap_or("No description available")
-                .to_string();
-            packages.push(Package {
-                name,
-                version,
-                description,
-                source: "official",
-            });
+/// The package names in a manifest written by `export_manifest`. Section
+/// headings like `[aur]`, blank lines and `#` comments, including the
+/// trailing version comments, are skipped.
+fn read_manifest(contents: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        let line = line.split_once('#').map_or(line, |(name, _)| name).trim();
+        if line.is_empty() || (line.starts_with('[') && line.ends_with(']')) {
+            continue;
+        }
+        if !names.iter().any(|name| name == line) {
+            names.push(line.to_string());
         }
     }
-    Ok(packages)
+    names
 }
 
-async fn search_aur(client: &Client, query: &str) -> Result<Vec<Package>, reqwest::Error> {
-    let url = format!(
-        "https://aur.archlinux.org/rpc/?v=5&type=search&arg={}",
-        urlencoding::encode(query)
-    );
-    let response = client.get(&url).send().await?;
-    let aur_data: AurResponse = response.json().await?;
+/// The outcome of one `doctor` check.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Check {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Runs every environment check, printing one line each. Returns false if
+/// any check failed outright.
+async fn doctor(client: &Client) -> bool {
+    let mut checks = Vec::new();
 
-    Ok(aur_data
-        .results
+    checks.push(if is_command_in_path("pacman") {
+        (Check::Pass, messages::doctor_found("pacman"))
+    } else {
+        (Check::Fail, messages::doctor_missing("pacman"))
+    });
+    if let Some(tool) = escalation().tool() {
+        checks.push(if is_command_in_path(tool) {
+            (Check::Pass, messages::doctor_found(tool))
+        } else {
+            (Check::Fail, messages::doctor_missing(tool))
+        });
+    }
+    let helpers: Vec<&str> = ["yay", "paru"]
         .into_iter()
-        .map(|pkg| Package {
-            name: pkg.name,
-            version: pkg.version,
-            description: pkg
-                .description
-                .unwrap_or_else(|| "No description available".to_string()),
-            source: "aur",
+        .filter(|helper| is_command_in_path(helper))
+        .collect();
+    checks.push(if helpers.is_empty() {
+        (Check::Warn, messages::doctor_no_aur_helper())
+    } else {
+        (Check::Pass, messages::doctor_found(&helpers.join(", ")))
+    });
+
+    for (_, url) in probe_urls() {
+        checks.push(if responds(client, &url, PRECHECK_TIMEOUT).await.is_ok() {
+            (Check::Pass, messages::doctor_reachable(&url))
+        } else {
+            (Check::Fail, messages::doctor_unreachable(&url))
+        });
+    }
+
+    checks.push(match fs::read_to_string(CONFIG_PATH) {
+        Ok(contents) => match toml::from_str::<Config>(&contents) {
+            Ok(_) => (Check::Pass, messages::doctor_config_valid(CONFIG_PATH)),
+            Err(e) => (Check::Fail, messages::doctor_config_invalid(CONFIG_PATH, e)),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            (Check::Warn, messages::doctor_config_missing(CONFIG_PATH))
+        }
+        Err(e) => (Check::Fail, messages::doctor_config_invalid(CONFIG_PATH, e)),
+    });
+
+    checks.push(match cache::check_writable() {
+        Ok(dir) => (Check::Pass, messages::doctor_cache_writable(dir.display())),
+        Err(e) => (Check::Fail, messages::doctor_cache_unwritable(e)),
+    });
+
+    for (check, message) in &checks {
+        let label = match check {
+            Check::Pass => messages::doctor_pass_label().green(),
+            Check::Warn => messages::doctor_warn_label().yellow(),
+            Check::Fail => messages::doctor_fail_label().red(),
+        };
+        println!("{label} {message}");
+    }
+    checks.iter().all(|(check, _)| *check != Check::Fail)
+}
+
+/// `$XDG_STATE_HOME/archlink`, where the install log and favorites live.
+fn state_dir() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(state_home) => PathBuf::from(state_home),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local").join("state"),
+    };
+    Some(dir.join("archlink"))
+}
+
+/// Writes `contents` to `path` by way of a temporary file in the same
+/// directory that is renamed over it, so a run killed mid-write leaves the
+/// old file intact rather than a truncated one. The install log is the one
+/// exception: it only ever appends a line, which can't clobber what's there.
+fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_with(path, contents.as_ref(), |from, to| fs::rename(from, to))
+}
+
+/// `write_atomic` with the final rename passed in, so tests can make it
+/// fail.
+fn write_atomic_with(
+    path: &Path,
+    contents: &[u8],
+    rename: impl FnOnce(&Path, &Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let name = path.file_name().ok_or(io::ErrorKind::InvalidInput)?;
+    let temp = path.with_file_name(format!(
+        ".{}.tmp-{}",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
         })
-        .collect())
+        .and_then(|()| rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
 }
 
-fn rank_results(
-    official: Vec<Package>,
-    aur: Vec<Package>,
-    query: &str,
-    max_results: usize,
-) -> Vec<Package> {
-    let mut combined: Vec<Package> = Vec::new();
-    combined.extend(official);
-    combined.extend(aur);
+/// Fails early with a readable error instead of letting a root pacman error
+/// out on systems without pacman.
+fn ensure_pacman() -> Result<(), String> {
+    if is_command_in_path("pacman") {
+        Ok(())
+    } else {
+        Err(format!("{}", messages::pacman_missing().red()))
+    }
+}
 
-    let query_words: Vec<&str> = query.split_whitespace().collect();
-    combined.sort_by(|a, b| {
-        let score_a = score_package(a, query, &query_words);
-        let score_b = score_package(b, query, &query_words);
-        score_b.cmp(&score_a)
-    });
+fn is_command_in_path(command: &str) -> bool {
+    let Some(path) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&path).any(|dir| {
+        fs::metadata(dir.join(command))
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    })
+}
+
+async fn self_update(client: &Client) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", messages::checking_self_update().bold().white());
+
+    let url = format!("{}/rpc/?v=5&type=info&arg[]=archlink", aur_base_url());
+    let aur_data: AurResponse = get_json(client, &url).await?;
+
+    let Some(latest) = aur_data.results.into_iter().next().map(|pkg| pkg.version) else {
+        println!("{}", messages::self_not_in_aur().yellow());
+        return Ok(());
+    };
+
+    if vercmp(&latest, VERSION) != Ordering::Greater {
+        println!("{}", messages::self_up_to_date(VERSION).green());
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        messages::self_update_available(VERSION, &latest)
+            .bold()
+            .white()
+    );
+
+    if !is_foreign_package("archlink") {
+        println!("{}", messages::self_not_from_aur().yellow());
+        return Ok(());
+    }
+
+    if confirm(&messages::self_update_confirm(), false)? {
+        if let Err(e) = install_package(
+            "archlink",
+            "aur",
+            &InstallOptions {
+                reinstall: true,
+                ..Default::default()
+            },
+        ) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    } else {
+        println!("{}", messages::upgrade_cancelled().yellow());
+    }
 
-    combined.truncate(max_results);
-    combined
+    Ok(())
 }
 
-fn score_package(pkg: &Package, query: &str, query_words: &[&str]) -> u32 {
-    let name_dist = levenshtein(&pkg.name, query) as u32;
-    let mut score = 1000 - name_dist;
+/// The names of every locally installed package.
+fn installed_packages() -> HashSet<String> {
+    SysCommand::new("pacman")
+        .arg("-Qq")
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-    let desc_lower = pkg.description.to_lowercase();
-    for word in query_words {
-        if desc_lower.contains(&word.to_lowercase()) {
-            score += 50;
+/// The locally installed version of `package`, if it is installed.
+fn installed_version(package: &str) -> Option<String> {
+    let output = SysCommand::new("pacman").args(["-Q", package]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // "name version"
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    stdout.split_whitespace().nth(1).map(String::from)
+}
+
+/// `archlink why`: the install reason and the packages that require, or
+/// optionally use, `package`, from `pacman -Qi`.
+fn explain_why(package: &str) -> Result<(), String> {
+    let info = installed_info(package)?;
+    let list = |key: &str| info_list(&info, key);
+
+    let explicit = info
+        .get("Install Reason")
+        .is_some_and(|reason| reason.starts_with("Explicitly"));
+    println!("{}", messages::install_reason(package, explicit).bold().white());
+    let required_by = list("Required By");
+    if required_by.is_empty() {
+        println!("{}", messages::required_by_nothing());
+    } else {
+        println!("{}", messages::required_by_header());
+        for name in required_by {
+            println!("  {}", name.green());
+        }
+    }
+    let optional_for = list("Optional For");
+    if !optional_for.is_empty() {
+        println!("{}", messages::optional_for_header());
+        for name in optional_for {
+            println!("  {}", name.green());
         }
     }
-    score
+    Ok(())
 }
 
-fn install_package(package: &str, source: &str) -> Result<(), String> {
-    let mut attempted = Vec::new();
+/// Removes an installed package with `pacman -R`, first listing the
+/// installed packages that require it. pacman won't break those, so without
+/// `--cascade` nothing is removed; with it they go too, via `pacman -Rc`,
+/// and the prompt lists everything that will be removed. Removals that take
+/// other packages along aren't confirmed by `--yes` alone.
+fn remove_package(package: &str, cascade: bool) -> Result<(), String> {
+    let info = installed_info(package)?;
+    let required_by = info_list(&info, "Required By");
+    if !required_by.is_empty() && !cascade {
+        println!("{}", messages::remove_required_by(package).yellow());
+        for name in &required_by {
+            println!("  {}", name.green());
+        }
+        return Err(format!("{}", messages::remove_needs_cascade(package).red()));
+    }
 
-    if source == "official" || source == "unknown" {
-        attempted.push("pacman");
-        println!(
-            "{}",
-            format!(
-                "Trying 'sudo pacman -S {package}'... (may prompt for password)"
-            )
+    // `-Rc` also takes the dependents' dependents, so ask pacman for the
+    // whole list rather than showing only the direct ones.
+    let mut also_removed: Vec<String> = Vec::new();
+    if !required_by.is_empty() {
+        also_removed = SysCommand::new("pacman")
+            .args(["-Rcp", "--print-format", "%n", package])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty() && *name != package)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_else(|| required_by.iter().map(|name| name.to_string()).collect());
+        println!("{}", messages::cascade_removes(package).yellow());
+        for name in &also_removed {
+            println!("  {}", name.green());
+        }
+    }
+
+    let prompt = messages::remove_confirm(package, also_removed.len());
+    let confirmed = if also_removed.is_empty() {
+        confirm(&prompt, false)
+    } else {
+        ask(&prompt, false)
+    };
+    if !confirmed.map_err(messages::error)? {
+        println!("{}", messages::remove_cancelled().yellow());
+        return Ok(());
+    }
+
+    let flag = if also_removed.is_empty() { "-R" } else { "-Rc" };
+    println!(
+        "{}",
+        messages::trying_command(&root_pacman(&format!("{flag} {package}")))
             .bold()
             .white()
-        );
-        let status = SysCommand::new("sudo")
-            .args(["pacman", "-S", package, "--noconfirm"])
-            .status()
-            .map_err(|e| format!("Failed to run pacman: {e}"))?;
-        if status.success() {
-            println!(
-                "{}",
-                format!("Successfully installed '{package}' with pacman").green()
-            );
-            return Ok(());
-        }
+    );
+    let status = pacman_as_root()?
+        .args([flag, package, "--noconfirm"])
+        .status()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    if !status.success() {
+        return Err(format!("{}", messages::remove_failed(package).red()));
     }
+    println!("{}", messages::removed(package, also_removed.len()).green());
+    Ok(())
+}
 
-    let helpers = [("yay", &["-S"] as &[&str]), ("paru", &["-S"])];
-    for (helper, args) in &helpers {
-        if is_command_in_path(helper) {
-            attempted.push(helper);
-            println!(
-                "{}",
-                format!(
-                    "Trying '{} {} {}'... (may prompt for password)",
-                    helper,
-                    args.join(" "),
-                    package
-                )
-                .bold()
-                .white()
-            );
-            let mut cmd_args = args.to_vec();
-            cmd_args.push(package);
-            let status = SysCommand::new(helper)
-                .args(&cmd_args)
-                .status()
-                .map_err(|e| format!("Failed to run {helper}: {e}"))?;
-            if status.success() {
-                println!(
-                    "{}",
-                    format!("Successfully installed '{package}' with {helper}").green()
-                );
-                return Ok(());
+/// `pacman -Qi` for an installed package, parsed into its fields.
+fn installed_info(package: &str) -> Result<HashMap<String, String>, String> {
+    ensure_pacman()?;
+    // The field names are translated otherwise.
+    let output = SysCommand::new("pacman")
+        .args(["-Qi", package])
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    if !output.status.success() {
+        return Err(format!("{}", messages::not_installed(package).yellow()));
+    }
+    Ok(parse_pacman_info(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The package names in a `pacman -Qi` list field, which reads `None`
+/// when empty.
+fn info_list<'a>(info: &'a HashMap<String, String>, key: &str) -> Vec<&'a str> {
+    info.get(key)
+        .map(|value| value.split_whitespace().filter(|name| *name != "None").collect())
+        .unwrap_or_default()
+}
+
+/// `pacman -Qi`'s `Key : value` lines. Long values wrap onto indented
+/// lines, which are joined back onto their key's value.
+fn parse_pacman_info(text: &str) -> HashMap<String, String> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut last: Option<String> = None;
+    for line in text.lines() {
+        if line.starts_with(char::is_whitespace) {
+            if let Some(value) = last.as_ref().and_then(|key| fields.get_mut(key)) {
+                value.push(' ');
+                value.push_str(line.trim());
             }
+        } else if let Some((key, value)) = line.split_once(" : ") {
+            let key = key.trim().to_string();
+            fields.insert(key.clone(), value.trim().to_string());
+            last = Some(key);
+        }
+    }
+    fields
+}
+
+/// `archlink stats`: counts of installed, explicit, foreign and orphaned
+/// packages, their total size, and the largest of them.
+fn system_stats() -> Result<(), String> {
+    ensure_pacman()?;
+    // `pacman -Q` exits non-zero when a filter matches nothing, e.g. when
+    // there are no orphans, which counts as 0.
+    let count = |flags: &str| {
+        SysCommand::new("pacman")
+            .arg(flags)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).lines().count())
+            .map_err(|e| messages::run_failed("pacman", e))
+    };
+    let total = count("-Qq")?;
+    let explicit = count("-Qqe")?;
+    let foreign = count("-Qqm")?;
+    let orphans = count("-Qqdt")?;
+
+    // The field names and size units are translated otherwise.
+    let output = SysCommand::new("pacman")
+        .arg("-Qi")
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|e| messages::run_failed("pacman", e))?;
+    let info = String::from_utf8_lossy(&output.stdout);
+    let mut sizes: Vec<(String, u64)> = info
+        .split("\n\n")
+        .map(parse_pacman_info)
+        .filter_map(|fields| {
+            let name = fields.get("Name")?.clone();
+            let size = parse_size(fields.get("Installed Size")?)?;
+            Some((name, size))
+        })
+        .collect();
+    let total_size: u64 = sizes.iter().map(|(_, size)| size).sum();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    println!("{}", messages::stats_header().bold().white());
+    let rows = [
+        (messages::stats_installed(), total.to_string()),
+        (messages::stats_explicit(), explicit.to_string()),
+        (messages::stats_foreign(), foreign.to_string()),
+        (messages::stats_orphans(), orphans.to_string()),
+        (messages::stats_total_size(), format_size(total_size)),
+    ];
+    let width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    for (label, value) in rows {
+        println!("  {label:<width$}  {}", value.green());
+    }
+    if !sizes.is_empty() {
+        println!();
+        println!("{}", messages::stats_largest().bold().white());
+        let width = sizes.iter().take(10).map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (i, (name, size)) in sizes.iter().take(10).enumerate() {
+            println!("  {:>2}. {name:<width$}  {}", i + 1, format_size(*size).blue());
         }
     }
+    Ok(())
+}
 
-    Err(format!(
-        "{}",
-        format!(
-            "Failed to install '{}'. Attempted: {}. Install yay/paru or check package name.",
-            package,
-            attempted.join(", ")
-        )
-        .red()
-    ))
+/// Parses sizes as `pacman -Qi` prints them, e.g. `12.34 MiB`.
+fn parse_size(text: &str) -> Option<u64> {
+    let (number, unit) = text.trim().split_once(' ')?;
+    let number: f64 = number.parse().ok()?;
+    let exponent = ["B", "KiB", "MiB", "GiB", "TiB"]
+        .iter()
+        .position(|known| *known == unit.trim())?;
+    Some((number * 1024f64.powi(exponent as i32)) as u64)
 }
 
-fn is_command_in_path(command: &str) -> bool {
-    SysCommand::new("which")
-        .arg(command)
+/// Returns true if `package` is installed but not available in any sync
+/// database, which is how pacman sees packages built from the AUR.
+fn is_foreign_package(package: &str) -> bool {
+    SysCommand::new("pacman")
+        .args(["-Qqm", package])
         .output()
         .is_ok_and(|output| output.status.success())
 }
+
+/// Compares two package versions the way pacman's `vercmp` does, taking the
+/// epoch and pkgrel into account when present.
+fn vercmp(a: &str, b: &str) -> Ordering {
+    let (epoch_a, ver_a, rel_a) = split_version(a);
+    let (epoch_b, ver_b, rel_b) = split_version(b);
+
+    rpmvercmp(epoch_a, epoch_b)
+        .then_with(|| rpmvercmp(ver_a, ver_b))
+        .then_with(|| match (rel_a, rel_b) {
+            (Some(rel_a), Some(rel_b)) => rpmvercmp(rel_a, rel_b),
+            _ => Ordering::Equal,
+        })
+}
+
+fn split_version(version: &str) -> (&str, &str, Option<&str>) {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => (epoch, rest),
+        _ => ("0", version),
+    };
+    match rest.rsplit_once('-') {
+        Some((ver, rel)) => (epoch, ver, Some(rel)),
+        None => (epoch, rest, None),
+    }
+}
+
+/// Port of the segment-wise comparison used by libalpm.
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (start_i, start_j) = (i, j);
+        while i < a.len() && !a[i].is_ascii_alphanumeric() {
+            i += 1;
+        }
+        while j < b.len() && !b[j].is_ascii_alphanumeric() {
+            j += 1;
+        }
+        if i >= a.len() || j >= b.len() {
+            break;
+        }
+        if i - start_i != j - start_j {
+            return (i - start_i).cmp(&(j - start_j));
+        }
+
+        let is_num = a[i].is_ascii_digit();
+        let (seg_start_i, seg_start_j) = (i, j);
+        if is_num {
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+        } else {
+            while i < a.len() && a[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_alphabetic() {
+                j += 1;
+            }
+        }
+
+        let mut seg_a = &a[seg_start_i..i];
+        let mut seg_b = &b[seg_start_j..j];
+        if seg_b.is_empty() {
+            return if is_num {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+        if is_num {
+            while seg_a.first() == Some(&b'0') {
+                seg_a = &seg_a[1..];
+            }
+            while seg_b.first() == Some(&b'0') {
+                seg_b = &seg_b[1..];
+            }
+            match seg_a.len().cmp(&seg_b.len()) {
+                Ordering::Equal => {}
+                other => return other,
+            }
+        }
+        match seg_a.cmp(seg_b) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+    }
+
+    let rest_a = &a[i..];
+    let rest_b = &b[j..];
+    if rest_a.is_empty() && rest_b.is_empty() {
+        Ordering::Equal
+    } else if (rest_a.is_empty() && !rest_b.first().is_some_and(u8::is_ascii_alphabetic))
+        || rest_a.first().is_some_and(u8::is_ascii_alphabetic)
+    {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
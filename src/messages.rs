@@ -0,0 +1,1243 @@
+//! User-facing strings. Every message is a function that matches on the
+//! selected language, so adding a `Lang` variant makes the compiler point at
+//! each message that still needs a translation. Colors are applied by the
+//! caller, never here.
+
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Languages with bundled translations.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    En,
+}
+
+impl Lang {
+    /// Parses tags like `en`, `en-GB` or `en_US.UTF-8`.
+    fn from_tag(tag: &str) -> Option<Lang> {
+        let primary = tag.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match primary.to_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Lang::En),
+            _ => None,
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Selects the output language from `--lang`, then `LC_ALL`, `LC_MESSAGES`
+/// and `LANG`, falling back to English. Only an explicit `--lang` for a
+/// language without a translation is reported.
+pub fn init(requested: Option<&str>) -> Option<String> {
+    let mut warning = None;
+    let lang = match requested {
+        Some(tag) => Lang::from_tag(tag).unwrap_or_else(|| {
+            warning = Some(unsupported_lang(tag));
+            Lang::En
+        }),
+        None => ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+            .next()
+            .and_then(|tag| Lang::from_tag(&tag))
+            .unwrap_or(Lang::En),
+    };
+    let _ = LANG.set(lang);
+    warning
+}
+
+fn lang() -> Lang {
+    LANG.get().copied().unwrap_or(Lang::En)
+}
+
+fn unsupported_lang(tag: &str) -> String {
+    match lang() {
+        Lang::En => format!("Warning: No translation for '{tag}'; using English."),
+    }
+}
+
+// Configuration
+
+pub fn invalid_config(e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Invalid config file format: {e}"),
+    }
+}
+
+pub fn config_read_failed(e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Failed to read config file: {e}"),
+    }
+}
+
+pub fn invalid_max_results_env(value: &str) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Ignoring non-numeric ARCHLINK_MAX_RESULTS '{value}'"),
+    }
+}
+
+pub fn unknown_install_tool(name: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Warning: Ignoring unknown tool '{name}' in install_order (expected pacman, yay or paru)"
+        ),
+    }
+}
+
+pub fn invalid_number_color(name: &str) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Unknown number_color '{name}'; using white."),
+    }
+}
+
+/// The first line of `archlink config show`.
+pub fn config_show_header(path: &str, found: bool) -> String {
+    match (lang(), found) {
+        (Lang::En, true) => format!("# Effective configuration, reading {path}"),
+        (Lang::En, false) => format!("# Effective configuration; {path} was not found"),
+    }
+}
+
+pub fn config_from_file() -> String {
+    match lang() {
+        Lang::En => "from the config file".to_string(),
+    }
+}
+
+pub fn config_from_env(var: &str) -> String {
+    match lang() {
+        Lang::En => format!("from {var}"),
+    }
+}
+
+pub fn config_from_default() -> String {
+    match lang() {
+        Lang::En => "default".to_string(),
+    }
+}
+
+// Arguments
+
+pub fn empty_query() -> String {
+    match lang() {
+        Lang::En => "Error: Query cannot be empty.".to_string(),
+    }
+}
+
+pub fn empty_package() -> String {
+    match lang() {
+        Lang::En => "Error: Package name cannot be empty.".to_string(),
+    }
+}
+
+pub fn json_array_needs_json() -> String {
+    match lang() {
+        Lang::En => "Error: --json-array only applies to JSON output.".to_string(),
+    }
+}
+
+pub fn error(e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Error: {e}"),
+    }
+}
+
+// Search
+
+pub fn unknown_field(field: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "unknown field '{field}' (expected name, version, description, source, updated, size, rdeps, votes, maintainer or outofdate)"
+        ),
+    }
+}
+
+/// Column headers of `search --table`: number, name, version, description,
+/// source, last update, installed size, reverse dependency count, votes,
+/// maintainer and out-of-date flag.
+#[cfg(feature = "table")]
+pub fn table_headers() -> [&'static str; 11] {
+    match lang() {
+        Lang::En => [
+            "#",
+            "Name",
+            "Version",
+            "Description",
+            "Source",
+            "Updated",
+            "Size",
+            "Required by",
+            "Votes",
+            "Maintainer",
+            "Out of date",
+        ],
+    }
+}
+
+pub fn testing_unstable() -> String {
+    match lang() {
+        Lang::En => "Warning: Packages from testing repos may be unstable.".to_string(),
+    }
+}
+
+pub fn searching_by_maintainer() -> String {
+    match lang() {
+        Lang::En => "Searching AUR by maintainer...".to_string(),
+    }
+}
+
+pub fn searching_aur() -> String {
+    match lang() {
+        Lang::En => "Searching AUR...".to_string(),
+    }
+}
+
+pub fn searching_all() -> String {
+    match lang() {
+        Lang::En => "Searching official repos and AUR...".to_string(),
+    }
+}
+
+pub fn search_progress(elapsed_secs: f64, timeout_secs: u64) -> String {
+    match lang() {
+        Lang::En => format!("searching... {elapsed_secs:.1}s / {timeout_secs}s"),
+    }
+}
+
+/// `source` is a `PackageSource::name`, e.g. `official` or `aur`.
+pub fn search_failed(source: &str, e: impl Display) -> String {
+    match (lang(), source) {
+        (Lang::En, "official") => format!("Warning: Official repo search failed: {e}"),
+        (Lang::En, "aur") => format!("Warning: AUR search failed: {e}"),
+        (Lang::En, _) => format!("Warning: {source} search failed: {e}"),
+    }
+}
+
+/// `source` is a `PackageSource::name` that answered without error.
+pub fn source_returned_nothing(source: &str) -> String {
+    match (lang(), source) {
+        (Lang::En, "official") => "The official repos returned 0 results.".to_string(),
+        (Lang::En, "aur") => "AUR returned 0 results.".to_string(),
+        (Lang::En, _) => format!("{source} returned 0 results."),
+    }
+}
+
+pub fn aur_rpc_error(error: &str) -> String {
+    match lang() {
+        Lang::En => format!("the AUR rejected the request: {error}"),
+    }
+}
+
+pub fn rank_export_failed(e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Failed to write ranking export: {e}"),
+    }
+}
+
+pub fn insecure_base_url(key: &str, url: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Error: {key} '{url}' doesn't use https://. Package queries are only sent over HTTPS; set allow_insecure = true to use it anyway, e.g. for a local test server."
+        ),
+    }
+}
+
+pub fn insecure_redirect(url: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "refused a redirect from HTTPS to plain HTTP ({url}); set allow_insecure = true to follow it"
+        ),
+    }
+}
+
+pub fn too_many_redirects() -> String {
+    match lang() {
+        Lang::En => "too many redirects".to_string(),
+    }
+}
+
+pub fn fixture_missing(url: &str, path: impl Display, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("No test fixture for {url}: couldn't read {path}: {e}"),
+    }
+}
+
+pub fn rate_limited(host: &str, retry_after: Option<u64>) -> String {
+    match (lang(), retry_after) {
+        (Lang::En, Some(secs)) => format!("{host} rate-limited the request; retry in {secs}s"),
+        (Lang::En, None) => format!("{host} rate-limited the request; retry later"),
+    }
+}
+
+pub fn no_description() -> String {
+    match lang() {
+        Lang::En => "No description available".to_string(),
+    }
+}
+
+pub fn base_url_unreachable(key: &str, url: &str, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Error: {url} (from {key} in the config) did not respond: {e}"),
+    }
+}
+
+pub fn appears_offline() -> String {
+    match lang() {
+        Lang::En => "Error: You appear to be offline; archlinux.org is unreachable. \
+                     Use --offline to search cached results, or --no-precheck to try anyway."
+            .to_string(),
+    }
+}
+
+pub fn no_cached_results(query: &str) -> String {
+    match lang() {
+        Lang::En => format!("No cached results for '{query}'. Run the search online first."),
+    }
+}
+
+/// Shown under results when a source failed, before the prompt.
+pub fn results_incomplete(failed: &[&str]) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Results are incomplete: {} could not be reached, so only what the other sources found is listed.",
+            source_names(failed)
+        ),
+    }
+}
+
+pub fn slow_sources(slow: &[&str], secs: u64) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Hint: {} took over {secs} seconds to answer. If that keeps happening, consider a mirror (official_base_url or aur_base_url in the config) or a longer --timeout.",
+            source_names(slow)
+        ),
+    }
+}
+
+pub fn no_packages_found(query: &str) -> String {
+    match lang() {
+        Lang::En => format!("No packages found for '{query}'. Try refining your query."),
+    }
+}
+
+/// Sources as named in a sentence, e.g. "the official repos, the AUR".
+fn source_names(sources: &[&str]) -> String {
+    sources
+        .iter()
+        .map(|source| match (lang(), *source) {
+            (Lang::En, "official") => "the official repos".to_string(),
+            (Lang::En, "aur") => "the AUR".to_string(),
+            (Lang::En, _) => source.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// No results while some sources failed, so the package may still exist.
+pub fn no_packages_found_incomplete(query: &str, failed: &[&str], searched: &[&str]) -> String {
+    let names = source_names;
+    match lang() {
+        Lang::En if searched.is_empty() => format!(
+            "No packages found for '{query}', but none of the sources could be reached ({}). Try again later.",
+            names(failed)
+        ),
+        Lang::En => format!(
+            "No packages found for '{query}' in {}, but {} could not be reached, so the package may still exist there.",
+            names(searched),
+            names(failed)
+        ),
+    }
+}
+
+/// The `--group-by-category` heading for official packages in no group.
+pub fn ungrouped() -> String {
+    match lang() {
+        Lang::En => "ungrouped".to_string(),
+    }
+}
+
+/// The `--group-by-category` heading for a source without package groups.
+pub fn no_groups_section(source: &str) -> String {
+    match (lang(), source) {
+        (Lang::En, "aur") => "AUR (no groups)".to_string(),
+        (Lang::En, _) => format!("{source} (no groups)"),
+    }
+}
+
+/// The badge on packages from a `trusted_maintainers` entry.
+pub fn trusted_badge() -> String {
+    match lang() {
+        Lang::En => "trusted".to_string(),
+    }
+}
+
+/// Shown as the maintainer of an AUR package that has none.
+pub fn orphan() -> String {
+    match lang() {
+        Lang::En => "orphan".to_string(),
+    }
+}
+
+/// `counts` holds each source with at least one result, in display order.
+pub fn suggestions_header(query: &str, counts: &[(&str, usize)]) -> String {
+    let counts: Vec<String> = counts.iter().map(|(source, n)| format!("{n} {source}")).collect();
+    match lang() {
+        Lang::En => format!("Suggestions for '{query}' ({}):", counts.join(", ")),
+    }
+}
+
+pub fn truncation_note(
+    shown: usize,
+    total: usize,
+    official: usize,
+    aur: usize,
+    flatpak: usize,
+) -> String {
+    match lang() {
+        Lang::En => {
+            let flatpak = if flatpak > 0 {
+                format!(", {flatpak} flatpak")
+            } else {
+                String::new()
+            };
+            format!(
+                "Showing {shown} of {total} matches ({official} official, {aur} aur{flatpak}); \
+                 use --limit to see more."
+            )
+        }
+    }
+}
+
+pub fn select_prompt(print_url: bool) -> String {
+    match (lang(), print_url) {
+        (Lang::En, false) => "Enter the number of the package to install (0 to exit): ".to_string(),
+        (Lang::En, true) => {
+            "Enter the number of the package to show the URL of (0 to exit): ".to_string()
+        }
+    }
+}
+
+pub fn invalid_selection() -> String {
+    match lang() {
+        Lang::En => "Invalid selection. Exiting.".to_string(),
+    }
+}
+
+// Prompts
+
+pub fn confirm_hint(default: bool) -> String {
+    match (lang(), default) {
+        (Lang::En, true) => "Y/n".to_string(),
+        (Lang::En, false) => "y/N".to_string(),
+    }
+}
+
+/// Whether `answer` to a confirmation prompt means yes.
+pub fn is_yes(answer: &str) -> bool {
+    match lang() {
+        Lang::En => answer.to_lowercase().starts_with('y'),
+    }
+}
+
+pub fn install_confirm(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Install '{package}'"),
+    }
+}
+
+pub fn install_cancelled() -> String {
+    match lang() {
+        Lang::En => "Installation cancelled.".to_string(),
+    }
+}
+
+// Package details
+
+pub fn package_not_found(package: &str) -> String {
+    match lang() {
+        Lang::En => {
+            format!("Warning: Package '{package}' was not found in the official repos or AUR.")
+        }
+    }
+}
+
+pub fn neither_package_exists() -> String {
+    match lang() {
+        Lang::En => "Neither package exists.".to_string(),
+    }
+}
+
+/// Row labels of `compare`: version, source, installed size, dependencies
+/// and AUR votes.
+pub fn compare_labels() -> [&'static str; 5] {
+    match lang() {
+        Lang::En => [
+            "version",
+            "source",
+            "installed size",
+            "dependencies",
+            "AUR votes",
+        ],
+    }
+}
+
+pub fn optdepends_lookup_failed(e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Failed to look up optional dependencies: {e}"),
+    }
+}
+
+pub fn optdepends_header(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Optional dependencies for '{package}':"),
+    }
+}
+
+pub fn optdepends_prompt() -> String {
+    match lang() {
+        Lang::En => {
+            "Enter the numbers of the dependencies to install, separated by spaces (0 to skip): "
+                .to_string()
+        }
+    }
+}
+
+// Installing
+
+pub fn pacman_missing() -> String {
+    match lang() {
+        Lang::En => "This doesn't look like an Arch system; pacman not found in PATH.".to_string(),
+    }
+}
+
+pub fn escalation_missing(tool: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "{tool} not found in PATH. Install it, or set 'escalation' in the config to sudo, pkexec, doas or none."
+        ),
+    }
+}
+
+pub fn empty_pinned_version(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Error: No version after '{package}='."),
+    }
+}
+
+pub fn aur_not_pinnable(package: &str, version: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "'{package}' is not in the official repos. AUR packages can't be pinned, since the AUR only builds the latest version; drop '={version}' to install that, or check out the version with install --clone-only."
+        ),
+    }
+}
+
+pub fn pinned_not_cached(package: &str, version: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "'{package}' {version} is not in pacman's package cache, and the repos only serve the latest version. Old versions are on the Arch Linux Archive."
+        ),
+    }
+}
+
+pub fn aur_not_downloadable(package: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "'{package}' is not in the official repos. AUR packages are built from source, so there is nothing to download; fetch the build files with install --clone-only."
+        ),
+    }
+}
+
+pub fn pinned_download(package: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "--download-only fetches the latest '{package}'; a pinned version must already be in pacman's cache."
+        ),
+    }
+}
+
+pub fn download_failed(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Failed to download '{package}'."),
+    }
+}
+
+pub fn downloaded_to(package: &str, path: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Downloaded '{package}' to {path}"),
+    }
+}
+
+pub fn overwrite_warning(glob: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Warning: files matching '{glob}' will be overwritten even if another package owns them. Only use --overwrite when you know where the conflicting files came from."
+        ),
+    }
+}
+
+pub fn ignore_arch_warning(host: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Warning: packages built for other architectures will be installed on this {host} machine. They may not run at all."
+        ),
+    }
+}
+
+pub fn arch_mismatch(package: &str, arch: &str, host: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "'{package}' was built for {arch}, but this machine is {host}. Pass --ignore-arch to install it anyway."
+        ),
+    }
+}
+
+pub fn already_installed(package: &str, version: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "'{package}' is already installed (version {version}); pass --reinstall to install it anyway."
+        ),
+    }
+}
+
+pub fn trying_command(command: &str) -> String {
+    match lang() {
+        Lang::En => format!("Trying '{command}'... (may prompt for password)"),
+    }
+}
+
+pub fn run_failed(tool: &str, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Failed to run {tool}: {e}"),
+    }
+}
+
+pub fn installed_with(package: &str, tool: &str) -> String {
+    match lang() {
+        Lang::En => format!("Successfully installed '{package}' with {tool}"),
+    }
+}
+
+pub fn install_failed(package: &str, attempted: &[&str]) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Failed to install '{package}'. Attempted: {}. Install yay/paru or check package name.",
+            attempted.join(", ")
+        ),
+    }
+}
+
+pub fn no_clone_dir() -> String {
+    match lang() {
+        Lang::En => "Error: No clone directory; set aur_clone_dir or pass --aur-clone-dir.".to_string(),
+    }
+}
+
+pub fn git_missing() -> String {
+    match lang() {
+        Lang::En => "git not found in PATH; install git to clone AUR packages.".to_string(),
+    }
+}
+
+pub fn aur_pull_confirm(path: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("{path} is already cloned. Pull the latest changes"),
+    }
+}
+
+pub fn aur_cloned(path: impl Display) -> String {
+    match lang() {
+        Lang::En => {
+            format!("Cloned to {path}. Review the PKGBUILD, then run 'makepkg -si' there.")
+        }
+    }
+}
+
+pub fn aur_clone_failed(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Failed to clone '{package}' from the AUR."),
+    }
+}
+
+pub fn install_log_empty() -> String {
+    match lang() {
+        Lang::En => "No installs logged yet.".to_string(),
+    }
+}
+
+// Favorites
+
+pub fn favorite_added(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Added '{package}' to the favorites."),
+    }
+}
+
+pub fn add_favorite_confirm(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Add '{package}' to the favorites"),
+    }
+}
+
+pub fn favorite_exists(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("'{package}' is already a favorite."),
+    }
+}
+
+pub fn favorite_removed(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Removed '{package}' from the favorites."),
+    }
+}
+
+pub fn not_a_favorite(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("'{package}' is not a favorite."),
+    }
+}
+
+pub fn no_favorites() -> String {
+    match lang() {
+        Lang::En => "No favorites yet. Add one with 'archlink fav add <package>'.".to_string(),
+    }
+}
+
+pub fn favorites_failed(failed: usize, total: usize) -> String {
+    match lang() {
+        Lang::En => format!("{failed} of {total} favorites failed to install."),
+    }
+}
+
+// Batch installs
+
+pub fn batch_progress(done: usize, total: usize) -> String {
+    match lang() {
+        Lang::En => format!("Looking up packages... {done}/{total}"),
+    }
+}
+
+pub fn batch_header(count: usize) -> String {
+    match lang() {
+        Lang::En => format!("Installing {count} packages:"),
+    }
+}
+
+/// One package in the batch list; `size` is its installed size, if known.
+pub fn batch_line(name: &str, version: &str, source: &str, size: Option<&str>) -> String {
+    match (lang(), size) {
+        (Lang::En, Some(size)) => format!("{name} {version} ({source}, {size})"),
+        (Lang::En, None) => format!("{name} {version} ({source})"),
+    }
+}
+
+pub fn batch_not_found(name: &str) -> String {
+    match lang() {
+        Lang::En => format!("{name} (not found in the official repos or the AUR; skipping)"),
+    }
+}
+
+pub fn batch_summary_header() -> String {
+    match lang() {
+        Lang::En => "Summary:".to_string(),
+    }
+}
+
+/// Column headers of the batch summary: result mark, package and details.
+#[cfg(feature = "table")]
+pub fn batch_summary_headers() -> [&'static str; 3] {
+    match lang() {
+        Lang::En => ["", "Package", "Result"],
+    }
+}
+
+pub fn batch_installed() -> String {
+    match lang() {
+        Lang::En => "installed".to_string(),
+    }
+}
+
+pub fn batch_reason_not_found() -> String {
+    match lang() {
+        Lang::En => "not found in the official repos or the AUR".to_string(),
+    }
+}
+
+pub fn batch_total_size(size: &str) -> String {
+    match lang() {
+        Lang::En => format!("Installed size of the official packages: {size}"),
+    }
+}
+
+pub fn lookup_failed(name: &str, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Warning: Could not look up '{name}': {e}"),
+    }
+}
+
+// Reverse dependencies
+
+pub fn not_installed(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("'{package}' is not installed."),
+    }
+}
+
+pub fn install_reason(package: &str, explicit: bool) -> String {
+    match (lang(), explicit) {
+        (Lang::En, true) => format!("'{package}' was installed explicitly."),
+        (Lang::En, false) => format!("'{package}' was installed as a dependency."),
+    }
+}
+
+pub fn required_by_header() -> String {
+    match lang() {
+        Lang::En => "Required by:".to_string(),
+    }
+}
+
+pub fn remove_required_by(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("These installed packages require '{package}':"),
+    }
+}
+
+pub fn remove_needs_cascade(package: &str) -> String {
+    match lang() {
+        Lang::En => format!(
+            "'{package}' was not removed, since pacman won't break the packages that require it. Pass --cascade to remove them too."
+        ),
+    }
+}
+
+pub fn cascade_removes(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Removing '{package}' also removes these packages that depend on it:"),
+    }
+}
+
+pub fn remove_confirm(package: &str, also_removed: usize) -> String {
+    match (lang(), also_removed) {
+        (Lang::En, 0) => format!("Remove '{package}'"),
+        (Lang::En, 1) => format!("Remove '{package}' and 1 package that depends on it"),
+        (Lang::En, n) => format!("Remove '{package}' and {n} packages that depend on it"),
+    }
+}
+
+pub fn remove_cancelled() -> String {
+    match lang() {
+        Lang::En => "Removal cancelled.".to_string(),
+    }
+}
+
+pub fn remove_failed(package: &str) -> String {
+    match lang() {
+        Lang::En => format!("Failed to remove '{package}'."),
+    }
+}
+
+pub fn removed(package: &str, also_removed: usize) -> String {
+    match (lang(), also_removed) {
+        (Lang::En, 0) => format!("Removed '{package}'."),
+        (Lang::En, 1) => format!("Removed '{package}' and 1 package that depended on it."),
+        (Lang::En, n) => format!("Removed '{package}' and {n} packages that depended on it."),
+    }
+}
+
+pub fn required_by_nothing() -> String {
+    match lang() {
+        Lang::En => "No installed package requires it.".to_string(),
+    }
+}
+
+pub fn optional_for_header() -> String {
+    match lang() {
+        Lang::En => "Optional for:".to_string(),
+    }
+}
+
+// Files
+
+pub fn files_db_missing() -> String {
+    match lang() {
+        Lang::En => "The pacman files database isn't synced yet; syncing it now.".to_string(),
+    }
+}
+
+pub fn files_db_refresh_failed() -> String {
+    match lang() {
+        Lang::En => "Syncing the files database with 'pacman -Fy' failed.".to_string(),
+    }
+}
+
+// Manifest
+
+pub fn manifest_written(official: usize, aur: usize, path: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Wrote {official} official and {aur} AUR packages to {path}"),
+    }
+}
+
+pub fn manifest_read_failed(path: impl Display, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Error: Could not read the manifest {path}: {e}"),
+    }
+}
+
+pub fn manifest_empty(path: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("No packages listed in {path}."),
+    }
+}
+
+// Cache
+
+pub fn cache_stats(
+    dir: impl Display,
+    entries: usize,
+    max_entries: usize,
+    size: &str,
+    max_size: &str,
+) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Cache directory: {dir}\nEntries: {entries} (at most {max_entries})\nSize: {size} (at most {max_size})"
+        ),
+    }
+}
+
+pub fn no_cache_dir() -> String {
+    match lang() {
+        Lang::En => "No cache directory: set HOME, XDG_CACHE_HOME or cache_dir in the config.".to_string(),
+    }
+}
+
+// Stats
+
+pub fn stats_header() -> String {
+    match lang() {
+        Lang::En => "Installed packages".to_string(),
+    }
+}
+
+pub fn stats_installed() -> String {
+    match lang() {
+        Lang::En => "Total:".to_string(),
+    }
+}
+
+pub fn stats_explicit() -> String {
+    match lang() {
+        Lang::En => "Explicitly installed:".to_string(),
+    }
+}
+
+pub fn stats_foreign() -> String {
+    match lang() {
+        Lang::En => "Foreign (AUR and local):".to_string(),
+    }
+}
+
+pub fn stats_orphans() -> String {
+    match lang() {
+        Lang::En => "Orphans:".to_string(),
+    }
+}
+
+pub fn stats_total_size() -> String {
+    match lang() {
+        Lang::En => "Total installed size:".to_string(),
+    }
+}
+
+pub fn stats_largest() -> String {
+    match lang() {
+        Lang::En => "Largest packages".to_string(),
+    }
+}
+
+// Doctor
+
+pub fn doctor_pass_label() -> String {
+    match lang() {
+        Lang::En => "[pass]".to_string(),
+    }
+}
+
+pub fn doctor_warn_label() -> String {
+    match lang() {
+        Lang::En => "[warn]".to_string(),
+    }
+}
+
+pub fn doctor_fail_label() -> String {
+    match lang() {
+        Lang::En => "[fail]".to_string(),
+    }
+}
+
+pub fn doctor_found(what: &str) -> String {
+    match lang() {
+        Lang::En => format!("Found {what}"),
+    }
+}
+
+pub fn doctor_missing(what: &str) -> String {
+    match lang() {
+        Lang::En => format!("{what} not found in PATH"),
+    }
+}
+
+pub fn doctor_no_aur_helper() -> String {
+    match lang() {
+        Lang::En => "No AUR helper found; install yay or paru to install AUR packages".to_string(),
+    }
+}
+
+pub fn doctor_reachable(url: &str) -> String {
+    match lang() {
+        Lang::En => format!("Reached {url}"),
+    }
+}
+
+pub fn doctor_unreachable(url: &str) -> String {
+    match lang() {
+        Lang::En => format!("Could not reach {url}"),
+    }
+}
+
+pub fn doctor_config_valid(path: &str) -> String {
+    match lang() {
+        Lang::En => format!("Config file {path} is valid"),
+    }
+}
+
+pub fn doctor_config_invalid(path: &str, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Config file {path} could not be loaded: {e}"),
+    }
+}
+
+pub fn doctor_config_missing(path: &str) -> String {
+    match lang() {
+        Lang::En => format!("No config file at {path}; using defaults"),
+    }
+}
+
+pub fn doctor_cache_writable(dir: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Cache directory {dir} is writable"),
+    }
+}
+
+pub fn doctor_cache_unwritable(e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("Cache directory is not writable: {e}"),
+    }
+}
+
+// Upgrading
+
+pub fn holding_back(packages: &[String]) -> String {
+    match lang() {
+        Lang::En => format!("Holding back: {}", packages.join(", ")),
+    }
+}
+
+pub fn partial_sync_detected(pending: usize) -> String {
+    match lang() {
+        Lang::En => format!(
+            "Warning: The package databases were synced without upgrading ({pending} upgrades pending). \
+             Installing packages in this state is a partial upgrade; this run finishes it."
+        ),
+    }
+}
+
+pub fn keyring_outdated() -> String {
+    match lang() {
+        Lang::En => "archlinux-keyring is out of date; upgrading it first so newer packager keys are trusted.".to_string(),
+    }
+}
+
+pub fn keyring_upgrade_failed() -> String {
+    match lang() {
+        Lang::En => "Upgrading archlinux-keyring failed.".to_string(),
+    }
+}
+
+pub fn running_command(command: &str) -> String {
+    match lang() {
+        Lang::En => format!("Running '{command}'... (may prompt for password)"),
+    }
+}
+
+pub fn pacman_upgrade_failed() -> String {
+    match lang() {
+        Lang::En => "System upgrade with pacman failed.".to_string(),
+    }
+}
+
+/// `downgrades` holds `(name, installed, repo)` versions.
+pub fn downgrades_pending(downgrades: &[(String, String, String)]) -> String {
+    let list: Vec<String> = downgrades
+        .iter()
+        .map(|(name, installed, repo)| format!("  {name} {installed} -> {repo}"))
+        .collect();
+    match lang() {
+        Lang::En => format!(
+            "Warning: these installed packages are newer than the repos, e.g. after a repo rollback. pacman left them alone; upgrading them would downgrade:\n{}",
+            list.join("\n")
+        ),
+    }
+}
+
+pub fn downgrade_hint() -> String {
+    match lang() {
+        Lang::En => {
+            "Run 'archlink upgrade --allow-downgrade' to downgrade them to the repo versions.".to_string()
+        }
+    }
+}
+
+pub fn downgrade_confirm(count: usize) -> String {
+    match lang() {
+        Lang::En => format!("Downgrade these {count} packages"),
+    }
+}
+
+pub fn downgrade_skipped() -> String {
+    match lang() {
+        Lang::En => "Skipped the downgrades.".to_string(),
+    }
+}
+
+pub fn downgrade_failed() -> String {
+    match lang() {
+        Lang::En => "Downgrading with pacman failed.".to_string(),
+    }
+}
+
+pub fn running_aur_upgrade(helper: &str) -> String {
+    match lang() {
+        Lang::En => format!("Running '{helper} -Sua' to upgrade AUR packages..."),
+    }
+}
+
+pub fn aur_upgrade_failed(helper: &str) -> String {
+    match lang() {
+        Lang::En => format!("AUR upgrade with {helper} failed."),
+    }
+}
+
+pub fn no_aur_helper_for_upgrade() -> String {
+    match lang() {
+        Lang::En => {
+            "No AUR helper found; skipped AUR upgrades. Install yay or paru to upgrade AUR packages."
+                .to_string()
+        }
+    }
+}
+
+pub fn upgrade_complete() -> String {
+    match lang() {
+        Lang::En => "System upgrade complete.".to_string(),
+    }
+}
+
+// Self-update
+
+pub fn checking_self_update() -> String {
+    match lang() {
+        Lang::En => "Checking for a newer archlink...".to_string(),
+    }
+}
+
+pub fn self_not_in_aur() -> String {
+    match lang() {
+        Lang::En => "Could not find archlink in the AUR.".to_string(),
+    }
+}
+
+pub fn self_up_to_date(version: &str) -> String {
+    match lang() {
+        Lang::En => format!("archlink {version} is up to date."),
+    }
+}
+
+pub fn self_update_available(current: &str, latest: &str) -> String {
+    match lang() {
+        Lang::En => format!("A newer archlink is available: {current} -> {latest}"),
+    }
+}
+
+pub fn self_not_from_aur() -> String {
+    match lang() {
+        Lang::En => {
+            "archlink was not installed from the AUR. Upgrade it with 'cargo install archlink --force'."
+                .to_string()
+        }
+    }
+}
+
+pub fn self_update_confirm() -> String {
+    match lang() {
+        Lang::En => "Upgrade archlink now".to_string(),
+    }
+}
+
+pub fn upgrade_cancelled() -> String {
+    match lang() {
+        Lang::En => "Upgrade cancelled.".to_string(),
+    }
+}
+
+// Verbose logs
+
+pub fn http_request_log(url: &str, status: impl Display, ms: u128, bytes: usize) -> String {
+    match lang() {
+        Lang::En => format!("[http] GET {url} -> {status} ({ms} ms, {bytes} bytes)"),
+    }
+}
+
+pub fn http_body_log(body: &str) -> String {
+    match lang() {
+        Lang::En => format!("[http] body: {body}"),
+    }
+}
+
+pub fn skipped_entry_log(e: impl Display, entry: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("[official] skipped an entry ({e}): {entry}"),
+    }
+}
+
+pub fn summary_source_log(source: &str, count: usize, ms: u128) -> String {
+    match lang() {
+        Lang::En => format!("[summary] {source}: {count} results in {ms} ms"),
+    }
+}
+
+pub fn summary_source_failed_log(source: &str, ms: u128, e: impl Display) -> String {
+    match lang() {
+        Lang::En => format!("[summary] {source}: failed after {ms} ms: {e}"),
+    }
+}
+
+pub fn summary_cache_log(status: &str) -> String {
+    match lang() {
+        Lang::En => format!("[summary] cache: {status}"),
+    }
+}
+
+pub fn summary_lookup_log(count: usize, ms: u128) -> String {
+    match lang() {
+        Lang::En => format!("[summary] looked up {count} packages in {ms} ms"),
+    }
+}
+
+pub fn summary_shown_log(shown: usize, total: usize) -> String {
+    match lang() {
+        Lang::En => format!("[summary] showing {shown} of {total} matches"),
+    }
+}
@@ -0,0 +1,216 @@
+//! The arrow-key selector behind `search --interactive`. The terminal is put
+//! into non-canonical mode with `stty` and restored when the picker returns,
+//! so no terminal library is needed.
+
+use crate::table;
+use colored::Colorize;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+/// How many items are visible at once; the list scrolls past this.
+const VISIBLE_ROWS: usize = 10;
+
+/// Whether the picker can run: both stdin and stdout are terminals and
+/// `stty` can change the terminal mode.
+pub fn available() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal() && stty(&["-g"]).is_some()
+}
+
+/// Lets the user move through `items` with the arrow keys and pick with
+/// Enter. Typing filters the list, Space toggles an item for multi-select,
+/// and Esc or Ctrl-C cancels. Returns the chosen indices into `items`, in
+/// list order, or nothing if cancelled. Enter with nothing toggled picks the
+/// highlighted item.
+pub fn pick(items: &[String]) -> io::Result<Vec<usize>> {
+    let _raw = RawMode::enable()?;
+    let mut state = State {
+        filter: String::new(),
+        cursor: 0,
+        scroll: 0,
+        toggled: vec![false; items.len()],
+    };
+    let mut stdout = io::stdout();
+    let mut drawn = 0;
+    write!(stdout, "\x1b[?25l")?;
+
+    let chosen = loop {
+        let visible = state.visible(items);
+        drawn = draw(&mut stdout, items, &visible, &mut state, drawn)?;
+
+        match read_key()? {
+            Key::Up => state.cursor = state.cursor.saturating_sub(1),
+            Key::Down => state.cursor = (state.cursor + 1).min(visible.len().saturating_sub(1)),
+            Key::Toggle => {
+                if let Some(&i) = visible.get(state.cursor) {
+                    state.toggled[i] = !state.toggled[i];
+                }
+            }
+            Key::Enter => {
+                let toggled: Vec<usize> = (0..items.len()).filter(|&i| state.toggled[i]).collect();
+                if !toggled.is_empty() {
+                    break toggled;
+                }
+                if let Some(&i) = visible.get(state.cursor) {
+                    break vec![i];
+                }
+            }
+            Key::Backspace => {
+                state.filter.pop();
+                state.cursor = 0;
+            }
+            Key::Char(c) => {
+                state.filter.push(c);
+                state.cursor = 0;
+            }
+            Key::Cancel => break Vec::new(),
+            Key::None => {}
+        }
+    };
+
+    clear(&mut stdout, drawn)?;
+    write!(stdout, "\x1b[?25h")?;
+    stdout.flush()?;
+    Ok(chosen)
+}
+
+struct State {
+    filter: String,
+    /// Position in the filtered list.
+    cursor: usize,
+    /// First filtered position on screen.
+    scroll: usize,
+    toggled: Vec<bool>,
+}
+
+impl State {
+    /// Indices of the items matching the filter, ignoring case.
+    fn visible(&self, items: &[String]) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        (0..items.len())
+            .filter(|&i| items[i].to_lowercase().contains(&filter))
+            .collect()
+    }
+}
+
+enum Key {
+    Up,
+    Down,
+    Enter,
+    Toggle,
+    Backspace,
+    Char(char),
+    Cancel,
+    /// The read timed out, or the key has no binding.
+    None,
+}
+
+/// Reads one key press. Reads time out after a tenth of a second (see
+/// `RawMode`), which is how a lone Esc is told apart from an arrow key.
+fn read_key() -> io::Result<Key> {
+    let Some(byte) = read_byte()? else {
+        return Ok(Key::None);
+    };
+    Ok(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b' ' => Key::Toggle,
+        0x7f | 0x08 => Key::Backspace,
+        0x03 => Key::Cancel,
+        0x10 => Key::Up,
+        0x0e => Key::Down,
+        0x1b => match (read_byte()?, read_byte()?) {
+            (None, _) => Key::Cancel,
+            (Some(b'[' | b'O'), Some(b'A')) => Key::Up,
+            (Some(b'[' | b'O'), Some(b'B')) => Key::Down,
+            _ => Key::None,
+        },
+        byte if byte.is_ascii_graphic() => Key::Char(char::from(byte)),
+        _ => Key::None,
+    })
+}
+
+fn read_byte() -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    let read = io::stdin().lock().read(&mut byte)?;
+    Ok((read == 1).then_some(byte[0]))
+}
+
+/// Redraws the picker over the `previous` lines drawn last time, scrolling
+/// to keep the cursor on screen, and returns how many lines it drew now.
+fn draw(
+    out: &mut impl Write,
+    items: &[String],
+    visible: &[usize],
+    state: &mut State,
+    previous: usize,
+) -> io::Result<usize> {
+    if state.cursor < state.scroll {
+        state.scroll = state.cursor;
+    } else if state.cursor >= state.scroll + VISIBLE_ROWS {
+        state.scroll = state.cursor + 1 - VISIBLE_ROWS;
+    }
+
+    clear(out, previous)?;
+    let width = table::terminal_width().saturating_sub(6);
+    let mut lines = 1;
+    write!(out, "{} {}\r\n", ">".bold().white(), state.filter)?;
+    for (pos, &i) in visible.iter().enumerate().skip(state.scroll).take(VISIBLE_ROWS) {
+        let mark = if state.toggled[i] { "[x]" } else { "[ ]" };
+        let text: String = items[i].chars().take(width).collect();
+        if pos == state.cursor {
+            write!(out, "{} {mark} {}\r\n", ">".cyan(), text.bold())?;
+        } else {
+            write!(out, "  {mark} {text}\r\n")?;
+        }
+        lines += 1;
+    }
+    out.flush()?;
+    Ok(lines)
+}
+
+/// Moves up over `lines` drawn lines and clears them.
+fn clear(out: &mut impl Write, lines: usize) -> io::Result<()> {
+    if lines > 0 {
+        write!(out, "\x1b[{lines}A")?;
+    }
+    write!(out, "\r\x1b[J")
+}
+
+/// Keeps the terminal in non-canonical, no-echo mode while alive. Signals
+/// are turned off too, so Ctrl-C reaches the picker as a key and the mode
+/// is always restored.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        let saved = stty(&["-g"]).ok_or(io::ErrorKind::Unsupported)?;
+        stty(&["-icanon", "-echo", "-isig", "min", "0", "time", "1"])
+            .ok_or(io::ErrorKind::Unsupported)?;
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+/// Runs `stty` on the controlling terminal, returning its output.
+fn stty(args: &[&str]) -> Option<String> {
+    let tty = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
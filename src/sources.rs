@@ -0,0 +1,352 @@
+//! Where search results come from. Each source is a `PackageSource`, and a
+//! search queries every configured source concurrently, so adding a source
+//! means implementing the trait and listing it in `configured`.
+
+use crate::{
+    AurResponse, OfficialPackage, OfficialResponse, Package, SearchOptions, Source, VERBOSITY,
+    aur_base_url, dates, get_json, is_testing_repo, messages, official_base_url,
+};
+use colored::Colorize;
+use reqwest::Client;
+use serde::Deserialize;
+use std::future::{Future, poll_fn};
+use std::pin::Pin;
+use std::sync::atomic;
+use std::task::Poll;
+
+pub type SearchResult = Result<Vec<Package>, Box<dyn std::error::Error>>;
+
+/// A boxed search future, so sources can live side by side in one list.
+pub type SearchFuture<'a> = Pin<Box<dyn Future<Output = SearchResult> + 'a>>;
+
+pub trait PackageSource {
+    /// The name results are tagged with, e.g. `official` or `aur`.
+    fn name(&self) -> Source;
+
+    fn search<'a>(&'a self, client: &'a Client, query: &'a str) -> SearchFuture<'a>;
+}
+
+/// The sources a search with `options` queries, in display order.
+pub fn configured(options: &SearchOptions) -> Vec<Box<dyn PackageSource>> {
+    let aur = Box::new(AurSource {
+        by: options.aur_by.clone(),
+    });
+    // Maintainers only exist in the AUR.
+    if options.aur_only || options.aur_by.as_deref() == Some("maintainer") {
+        return vec![aur];
+    }
+    let mut sources: Vec<Box<dyn PackageSource>> = vec![
+        Box::new(OfficialSource {
+            include_testing: options.include_testing,
+        }),
+        aur,
+    ];
+    if options.include_flatpak {
+        sources.push(Box::new(FlatpakSource));
+    }
+    sources
+}
+
+/// Polls every future until all are done, returning their outputs in order.
+pub async fn join_all<T>(futures: Vec<Pin<Box<dyn Future<Output = T> + '_>>>) -> Vec<T> {
+    let mut pending: Vec<_> = futures.into_iter().map(Some).collect();
+    let mut outputs: Vec<Option<T>> = pending.iter().map(|_| None).collect();
+    poll_fn(|cx| {
+        for (slot, output) in pending.iter_mut().zip(outputs.iter_mut()) {
+            if let Some(future) = slot
+                && let Poll::Ready(value) = future.as_mut().poll(cx)
+            {
+                *output = Some(value);
+                *slot = None;
+            }
+        }
+        if pending.iter().all(Option::is_none) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+/// A description fit for a one-line row: control characters such as
+/// newlines, tabs and escape codes become spaces, and runs of whitespace
+/// collapse into one. Empty descriptions get the placeholder.
+fn clean_description(raw: &str) -> String {
+    let spaced: String = raw
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let description = spaced.split_whitespace().collect::<Vec<_>>().join(" ");
+    if description.is_empty() {
+        messages::no_description()
+    } else {
+        description
+    }
+}
+
+/// The official repos, via the archlinux.org package search.
+pub struct OfficialSource {
+    pub include_testing: bool,
+}
+
+impl PackageSource for OfficialSource {
+    fn name(&self) -> Source {
+        "official"
+    }
+
+    fn search<'a>(&'a self, client: &'a Client, query: &'a str) -> SearchFuture<'a> {
+        Box::pin(async move {
+            let url = format!(
+                "{}/packages/search/json/?q={}",
+                official_base_url(),
+                urlencoding::encode(query)
+            );
+            let response: OfficialResponse = get_json(client, &url).await?;
+            self.packages(response)
+        })
+    }
+}
+
+impl OfficialSource {
+    /// The packages in a search response. An entry that doesn't parse is
+    /// skipped rather than failing the whole search, but if none of them
+    /// parse the response has likely changed shape, and the first error is
+    /// returned instead of an empty result.
+    fn packages(&self, response: OfficialResponse) -> SearchResult {
+        let mut packages = Vec::new();
+        let mut first_error = None;
+        let mut parsed = 0;
+        for entry in response.results {
+            let pkg = match OfficialPackage::deserialize(&entry) {
+                Ok(pkg) => pkg,
+                Err(e) => {
+                    if VERBOSITY.load(atomic::Ordering::Relaxed) >= 1 {
+                        eprintln!("{}", messages::skipped_entry_log(&e, &entry).dimmed());
+                    }
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            parsed += 1;
+            if !self.include_testing && pkg.repo.as_deref().is_some_and(is_testing_repo) {
+                continue;
+            }
+            packages.push(Package {
+                version: pkg.version(),
+                description: clean_description(pkg.pkgdesc.as_deref().unwrap_or("")),
+                source: self.name(),
+                last_updated: pkg.last_update.as_deref().and_then(dates::parse_rfc3339),
+                installed_size: pkg.installed_size,
+                reverse_deps: pkg.required_by.as_ref().map(Vec::len),
+                name: pkg.pkgname,
+                repo: pkg.repo,
+                arch: pkg.arch,
+                groups: pkg.groups,
+                ..Default::default()
+            });
+        }
+        match first_error {
+            Some(e) if parsed == 0 => Err(e.into()),
+            _ => Ok(packages),
+        }
+    }
+}
+
+/// The AUR RPC search, optionally by a field other than name and description.
+/// The RPC has no sort or limit parameters, so every match comes back and
+/// all sorting happens in `rank_results`.
+pub struct AurSource {
+    pub by: Option<String>,
+}
+
+impl PackageSource for AurSource {
+    fn name(&self) -> Source {
+        "aur"
+    }
+
+    fn search<'a>(&'a self, client: &'a Client, query: &'a str) -> SearchFuture<'a> {
+        Box::pin(async move {
+            let mut url = format!(
+                "{}/rpc/?v=5&type=search&arg={}",
+                aur_base_url(),
+                urlencoding::encode(query)
+            );
+            if let Some(by) = &self.by {
+                url.push_str(&format!("&by={by}"));
+            }
+            let aur_data: AurResponse = get_json(client, &url).await?;
+            self.packages(aur_data)
+        })
+    }
+}
+
+impl AurSource {
+    /// The packages in an RPC response, or the error the RPC reported.
+    fn packages(&self, aur_data: AurResponse) -> SearchResult {
+        if let Some(error) = aur_data.error {
+            return Err(messages::aur_rpc_error(&error).into());
+        }
+        if aur_data.result_count == 0 {
+            return Ok(Vec::new());
+        }
+
+        Ok(aur_data
+            .results
+            .into_iter()
+            .map(|pkg| Package {
+                name: pkg.name,
+                version: pkg.version,
+                description: clean_description(pkg.description.as_deref().unwrap_or("")),
+                source: self.name(),
+                last_updated: pkg.last_modified,
+                votes: Some(pkg.num_votes),
+                maintainer: pkg.maintainer,
+                out_of_date: pkg.out_of_date,
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+/// Flatpak remotes, via the local `flatpak` binary. Enabled by
+/// `enable_flatpak` in the config.
+pub struct FlatpakSource;
+
+impl PackageSource for FlatpakSource {
+    fn name(&self) -> Source {
+        "flatpak"
+    }
+
+    fn search<'a>(&'a self, _client: &'a Client, query: &'a str) -> SearchFuture<'a> {
+        Box::pin(async move {
+            let output = tokio::process::Command::new("flatpak")
+                .args(["search", "--columns=application,version,description", query])
+                .output()
+                .await?;
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+            }
+
+            // One tab-separated row per app; "No matches found" has no tabs.
+            Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let mut columns = line.split('\t');
+                    let name = columns.next()?.trim();
+                    let version = columns.next()?.trim();
+                    let description = columns.next().unwrap_or_default();
+                    Some(Package {
+                        name: name.to_string(),
+                        version: version.to_string(),
+                        description: clean_description(description),
+                        source: self.name(),
+                        ..Default::default()
+                    })
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn official_fixture(query: &str) -> OfficialResponse {
+        let path = format!("fixtures/packages_search_json__q_{query}.json");
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn aur_fixture(query: &str) -> AurResponse {
+        let path = format!("fixtures/rpc__v_5_type_search_arg_{query}.json");
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn aur_resultcount_zero_is_an_empty_search() {
+        let aur = AurSource { by: None };
+        let response = aur_fixture("nosuchpackage");
+        assert_eq!(response.result_count, 0);
+        assert!(aur.packages(response).unwrap().is_empty());
+
+        let packages = aur.packages(aur_fixture("vim")).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].votes, Some(12));
+
+        let rejected = r#"{"resultcount": 0, "results": [], "error": "Too many package results."}"#;
+        let rejected: AurResponse = serde_json::from_str(rejected).unwrap();
+        let error = aur.packages(rejected).unwrap_err();
+        assert!(error.to_string().contains("Too many package results."));
+    }
+
+    #[test]
+    fn official_entries_that_dont_parse_are_skipped() {
+        let official = OfficialSource {
+            include_testing: false,
+        };
+        let response = official_fixture("vim");
+        let unnamed = |entry: &&serde_json::Value| entry.get("pkgname").is_none();
+        assert_eq!(response.results.iter().filter(unnamed).count(), 1);
+        let packages = official.packages(response).unwrap();
+        let names: Vec<&str> = packages.iter().map(|pkg| pkg.name.as_str()).collect();
+        assert_eq!(names, ["vim", "gvim"]);
+    }
+
+    #[test]
+    fn official_response_with_no_parsable_entries_fails() {
+        let official = OfficialSource {
+            include_testing: false,
+        };
+        let unparsable: OfficialResponse =
+            serde_json::from_str(r#"{"results": [{"pkgver": "1"}, {"pkgname": 1}]}"#).unwrap();
+        let error = official.packages(unparsable).unwrap_err();
+        assert!(error.to_string().contains("pkgname"), "{error}");
+
+        let empty: OfficialResponse = serde_json::from_str(r#"{"results": []}"#).unwrap();
+        assert!(official.packages(empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn official_required_by_counts_reverse_deps() {
+        let official = OfficialSource {
+            include_testing: false,
+        };
+        let packages = official.packages(official_fixture("vim")).unwrap();
+        let counts: Vec<Option<usize>> = packages.iter().map(|pkg| pkg.reverse_deps).collect();
+        assert_eq!(counts, [Some(2), Some(0)]);
+
+        let without: OfficialResponse =
+            serde_json::from_str(r#"{"results": [{"pkgname": "vim"}]}"#).unwrap();
+        assert_eq!(official.packages(without).unwrap()[0].reverse_deps, None);
+    }
+
+    #[test]
+    fn clean_description_flattens_control_characters() {
+        let raw = "A\ttext\n\n  editor\x1b[31m";
+        assert_eq!(clean_description(raw), "A text editor [31m");
+        assert_eq!(clean_description(" \r\n "), messages::no_description());
+        assert_eq!(clean_description("Vi Improved"), "Vi Improved");
+    }
+
+    #[test]
+    fn official_entries_parse_into_typed_packages() {
+        let response = official_fixture("vim");
+        let typed = OfficialPackage::deserialize(&response.results[0]).unwrap();
+        assert_eq!(typed.version(), "9.1.0866-1");
+        assert!(typed.depends.is_empty());
+
+        let official = OfficialSource {
+            include_testing: false,
+        };
+        let vim = official.packages(response).unwrap().remove(0);
+        assert_eq!(vim.name, "vim");
+        assert_eq!(vim.version, "9.1.0866-1");
+        assert_eq!(vim.source, "official");
+        assert_eq!(vim.repo.as_deref(), Some("extra"));
+        assert_eq!(vim.arch.as_deref(), Some("x86_64"));
+        assert_eq!(vim.installed_size, Some(4_523_012));
+        assert_eq!(vim.last_updated, Some(1_731_578_531));
+    }
+}
@@ -0,0 +1,140 @@
+//! Box-drawn tables for `search --table`. Cells are plain text; colors are
+//! applied per line by the caller's `paint`, after widths are measured, so
+//! escape codes never skew the alignment. Only `terminal_width` is built
+//! without the `table` feature, since other layouts need it too.
+
+use std::env;
+use std::fs::File;
+use std::process::Command;
+
+/// Used when neither `COLUMNS` nor the terminal report a width.
+const FALLBACK_WIDTH: usize = 100;
+
+/// The narrowest a wrapped column gets, however small the terminal.
+#[cfg(feature = "table")]
+const MIN_WRAP_WIDTH: usize = 20;
+
+/// Renders `rows` under `headers`. If the table is wider than the terminal,
+/// column `wrap` is narrowed and its cells wrapped at word boundaries.
+#[cfg(feature = "table")]
+pub fn render(
+    headers: &[&str],
+    rows: &[Vec<String>],
+    wrap: Option<usize>,
+    paint: impl Fn(usize, &str) -> String,
+) -> Vec<String> {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    if let Some(col) = wrap {
+        // Each column takes its width plus "│ " and a trailing space; the
+        // closing border takes one more.
+        let others: usize = widths
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != col)
+            .map(|(_, width)| width + 3)
+            .sum();
+        let available = terminal_width().saturating_sub(others + 4);
+        widths[col] = widths[col].min(available.max(MIN_WRAP_WIDTH));
+    }
+
+    let border = |left: &str, mid: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+        format!("{left}{}{right}", segments.join(mid))
+    };
+
+    let mut lines = vec![border("┌", "┬", "┐")];
+    let header_cells: Vec<Vec<String>> = headers.iter().map(|h| vec![h.to_string()]).collect();
+    lines.extend(render_row(&header_cells, &widths, |_, text| text.to_string()));
+    lines.push(border("├", "┼", "┤"));
+    for row in rows {
+        let cells: Vec<Vec<String>> = row
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| {
+                if Some(col) == wrap {
+                    wrap_text(cell, widths[col])
+                } else {
+                    vec![cell.clone()]
+                }
+            })
+            .collect();
+        lines.extend(render_row(&cells, &widths, &paint));
+    }
+    lines.push(border("└", "┴", "┘"));
+    lines
+}
+
+/// One table row, which spans as many lines as its tallest cell.
+#[cfg(feature = "table")]
+fn render_row(
+    cells: &[Vec<String>],
+    widths: &[usize],
+    paint: impl Fn(usize, &str) -> String,
+) -> Vec<String> {
+    let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+    (0..height)
+        .map(|line| {
+            let mut out = String::from("│");
+            for (col, (cell, width)) in cells.iter().zip(widths).enumerate() {
+                let text = cell.get(line).map(String::as_str).unwrap_or_default();
+                let padding = width.saturating_sub(text.chars().count());
+                out.push(' ');
+                out.push_str(&paint(col, text));
+                out.push_str(&" ".repeat(padding + 1));
+                out.push('│');
+            }
+            out
+        })
+        .collect()
+}
+
+/// Splits `text` into lines of at most `width` characters, breaking between
+/// words where possible and inside words longer than a whole line.
+#[cfg(feature = "table")]
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+        let word: String = word.into_iter().collect();
+        let needed = current.chars().count() + word.chars().count() + 1;
+        if !current.is_empty() && needed > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// `COLUMNS` if set, else what `stty` reports for the controlling terminal.
+pub fn terminal_width() -> usize {
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return columns;
+    }
+    File::open("/dev/tty")
+        .ok()
+        .and_then(|tty| Command::new("stty").arg("size").stdin(tty).output().ok())
+        .and_then(|output| {
+            let size = String::from_utf8(output.stdout).ok()?;
+            size.split_whitespace().nth(1)?.parse().ok()
+        })
+        .unwrap_or(FALLBACK_WIDTH)
+}
@@ -0,0 +1,460 @@
+use super::*;
+use std::io::Read;
+use std::net::TcpListener;
+use std::thread;
+
+fn package(name: &str, source: Source) -> Package {
+    Package {
+        name: name.to_string(),
+        source,
+        ..Default::default()
+    }
+}
+
+/// What `rank_results` returns for `query`, in order, as `name/source`.
+fn ranked(candidates: &[Package], query: &str, options: &SearchOptions) -> Vec<String> {
+    rank_results(candidates.to_vec(), query, options)
+        .packages
+        .iter()
+        .map(|pkg| format!("{}/{}", pkg.name, pkg.source))
+        .collect()
+}
+
+#[test]
+fn partial_sort_matches_full_sort() {
+    // Every name is two edits from the query, so all of them tie on score.
+    let candidates = [
+        package("qd", "aur"),
+        package("qb", "official"),
+        package("qa", "aur"),
+        package("qb", "aur"),
+        package("qe", "official"),
+        package("qc", "official"),
+    ];
+    let full = ranked(&candidates, "zz", &SearchOptions::default());
+    for max_results in 1..candidates.len() {
+        let options = SearchOptions {
+            max_results,
+            ..Default::default()
+        };
+        assert_eq!(ranked(&candidates, "zz", &options), full[..max_results]);
+    }
+}
+
+/// Times ranking 5000 packages down to 10 against a full sort, which is what
+/// `rank_results` did before it only sorted the top entries. Run with
+/// `cargo test --release -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn time_partial_sort_on_5000_packages() {
+    let sources = ["official", "aur", "flatpak"];
+    let candidates: Vec<Package> = (0..5000)
+        .map(|i| package(&format!("vim-{}", i * 7919 % 5000), sources[i % 3]))
+        .collect();
+    let time = |max_results: usize| {
+        let options = SearchOptions {
+            max_results,
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let mut top = Vec::new();
+        for _ in 0..20 {
+            top = ranked(&candidates, "vim-2500", &options);
+        }
+        (top, started.elapsed() / 20)
+    };
+    let (top, partial) = time(10);
+    let (full, sorted) = time(0);
+    assert_eq!(top, full[..10]);
+    println!("top 10 of 5000: {partial:?} with the partial sort, {sorted:?} with a full sort");
+}
+
+#[test]
+fn names_match_case_insensitively_by_default() {
+    let breakdown = |name: &str, options: &SearchOptions| {
+        score_breakdown(&package(name, "official"), "Firefox", &["Firefox"], options)
+    };
+    let options = SearchOptions::default();
+    assert_eq!(breakdown("firefox", &options).name_distance, 0);
+    assert_eq!(
+        breakdown("firefox", &options).score,
+        breakdown("Firefox", &options).score
+    );
+
+    let sensitive = SearchOptions {
+        case_sensitive: true,
+        ..Default::default()
+    };
+    assert_eq!(breakdown("firefox", &sensitive).name_distance, 1);
+    let candidates = [package("firefox", "official"), package("Firefox", "aur")];
+    assert_eq!(ranked(&candidates, "Firefox", &sensitive)[0], "Firefox/aur");
+}
+
+#[cfg(feature = "fuzzy")]
+#[test]
+fn fuzzy_distance_scores_subsequences() {
+    assert_eq!(fuzzy_distance("vim", "vim"), 0);
+    assert!(fuzzy_distance("firefox", "ff") < 100);
+    assert!(fuzzy_distance("firefox", "fox") < fuzzy_distance("firefox", "ff"));
+    // Skipping to a word start costs half as much as skipping mid-word.
+    assert!(fuzzy_distance("plugins-base", "pb") < fuzzy_distance("pluginsxbase", "pb"));
+    // Anything that isn't a subsequence ranks below everything that is.
+    assert!(fuzzy_distance("vim", "xyz") >= 100);
+    assert!(fuzzy_distance("vim", "mv") > fuzzy_distance("a-very-long-name-with-vim", "vim"));
+}
+
+#[cfg(feature = "fuzzy")]
+#[test]
+fn fuzzy_and_levenshtein_rank_differently() {
+    let candidates = [
+        package("firefox", "official"),
+        package("ffmpeg", "official"),
+        package("fx", "aur"),
+    ];
+    let order = |match_algorithm: MatchAlgorithm| {
+        let options = SearchOptions {
+            match_algorithm,
+            ..Default::default()
+        };
+        ranked(&candidates, "ff", &options)
+    };
+    // `fx` is one edit from `ff`, but `ff` isn't a subsequence of it.
+    let levenshtein = order(MatchAlgorithm::Levenshtein);
+    assert_eq!(
+        levenshtein,
+        ["fx/aur", "ffmpeg/official", "firefox/official"]
+    );
+    let fuzzy = order(MatchAlgorithm::Fuzzy);
+    assert_eq!(fuzzy, ["ffmpeg/official", "firefox/official", "fx/aur"]);
+}
+
+#[test]
+fn shuffle_is_stable_for_a_seed() {
+    let shuffled = |seed: u64| {
+        let mut items: Vec<u32> = (0..20).collect();
+        shuffle(&mut items, seed);
+        items
+    };
+    assert_eq!(shuffled(42), shuffled(42));
+    assert_ne!(shuffled(42), shuffled(43));
+    let mut sorted = shuffled(42);
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+}
+
+#[test]
+fn ties_are_ordered_by_name_then_source() {
+    let candidates = [
+        package("qb", "flatpak"),
+        package("qb", "aur"),
+        package("qa", "aur"),
+        package("qb", "official"),
+    ];
+    let order = ranked(&candidates, "zz", &SearchOptions::default());
+    assert_eq!(order, ["qa/aur", "qb/official", "qb/aur", "qb/flatpak"]);
+}
+
+#[test]
+fn min_votes_only_drops_aur_packages() {
+    let with_votes = |source: Source, votes: Option<u64>| Package {
+        votes,
+        ..package("vim", source)
+    };
+    assert!(meets_min_votes(&package("vim", "official"), 10));
+    assert!(meets_min_votes(&package("vim", "flatpak"), 10));
+    assert!(meets_min_votes(&with_votes("aur", Some(10)), 10));
+    assert!(!meets_min_votes(&with_votes("aur", Some(9)), 10));
+    assert!(!meets_min_votes(&with_votes("aur", None), 10));
+}
+
+/// Answers one connection on a local port per entry of `responses`, in
+/// order, and returns the base URL and a handle to the requests received.
+fn serve(responses: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+            requests.push(String::from_utf8_lossy(&request).into_owned());
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+    (url, server)
+}
+
+const OK_RESPONSE: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+
+#[tokio::test]
+async fn requests_send_the_user_agent() {
+    let cases = [
+        ("", format!("archlink/{VERSION}")),
+        ("user_agent = \"custom/1.0\"", "custom/1.0".to_string()),
+    ];
+    for (config, user_agent) in cases {
+        let config: Config = toml::from_str(config).unwrap();
+        let client = build_client(&config, true, DEFAULT_TIMEOUT).unwrap();
+        let (url, server) = serve(vec![OK_RESPONSE]);
+        let _: serde_json::Value = get_json(&client, &url).await.unwrap();
+        let request = server.join().unwrap().remove(0).to_lowercase();
+        assert!(
+            request.contains(&format!("\r\nuser-agent: {user_agent}\r\n")),
+            "{request}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn rate_limited_requests_retry_within_the_deadline() {
+    let client = build_client(&toml::from_str("").unwrap(), true, DEFAULT_TIMEOUT).unwrap();
+    let limited = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\n\
+                   Content-Length: 0\r\nConnection: close\r\n\r\n";
+    let (url, server) = serve(vec![limited, OK_RESPONSE]);
+    let started = Instant::now();
+    let _: serde_json::Value = get_json(&client, &url).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.join().unwrap().len(), 2);
+
+    // A wait that outlasts the timeout is reported instead of waited out.
+    let limited = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 60\r\n\
+                   Content-Length: 0\r\nConnection: close\r\n\r\n";
+    let (url, server) = serve(vec![limited]);
+    let started = Instant::now();
+    let error = get_json::<serde_json::Value>(&client, &url)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("retry in 60s"), "{error}");
+    assert!(started.elapsed() < DEFAULT_TIMEOUT);
+    server.join().unwrap();
+}
+
+#[test]
+fn prefer_wins_near_ties() {
+    // `vim-x` is one edit further from the query than `vimx`.
+    let candidates = [package("vimx", "aur"), package("vim-x", "official")];
+    let options = SearchOptions {
+        prefer: Some("official".to_string()),
+        prefer_margin: 5,
+        ..Default::default()
+    };
+    let breakdown = score_breakdown(&candidates[1], "vim", &["vim"], &options);
+    assert_eq!(breakdown.prefer_bonus, 5.0);
+    assert_eq!(ranked(&candidates, "vim", &options)[0], "vim-x/official");
+    assert_eq!(
+        ranked(&candidates, "vim", &SearchOptions::default())[0],
+        "vimx/aur"
+    );
+}
+
+#[test]
+fn merge_arches_folds_official_duplicates() {
+    let with_arch = |name: &str, source: Source, arch: &str| Package {
+        arch: Some(arch.to_string()),
+        ..package(name, source)
+    };
+    let merged = merge_arches(vec![
+        with_arch("glibc", "official", "x86_64"),
+        with_arch("glibc-git", "aur", "x86_64"),
+        with_arch("glibc", "official", "i686"),
+        with_arch("glibc", "official", "x86_64"),
+        with_arch("glibc-git", "aur", "x86_64"),
+    ]);
+    let names: Vec<&str> = merged.iter().map(|pkg| pkg.name.as_str()).collect();
+    assert_eq!(names, ["glibc", "glibc-git", "glibc-git"]);
+    assert_eq!(merged[0].arch.as_deref(), Some("x86_64"));
+    assert_eq!(merged[0].arches, ["x86_64", "i686"]);
+    assert!(merged[1].arches.is_empty());
+}
+
+#[test]
+fn sort_by_reverse_deps_puts_unknown_counts_last() {
+    let with_rdeps = |name: &str, reverse_deps: Option<usize>| Package {
+        reverse_deps,
+        ..package(name, "official")
+    };
+    let candidates = [
+        with_rdeps("qa", None),
+        with_rdeps("qb", Some(1)),
+        with_rdeps("qc", Some(7)),
+    ];
+    let options = SearchOptions {
+        sort: SortBy::ReverseDeps,
+        ..Default::default()
+    };
+    let order = ranked(&candidates, "zz", &options);
+    assert_eq!(order, ["qc/official", "qb/official", "qa/official"]);
+}
+
+#[test]
+fn matches_every_word_checks_name_and_description() {
+    let pkg = Package {
+        description: "Vi Improved, a highly configurable text editor".to_string(),
+        ..package("gvim", "official")
+    };
+    assert!(matches_every_word(&pkg, "gvim editor", false));
+    assert!(matches_every_word(&pkg, "VIM Text", false));
+    assert!(!matches_every_word(&pkg, "VIM Text", true));
+    assert!(!matches_every_word(&pkg, "vim emacs", false));
+}
+
+#[test]
+fn a_limit_of_zero_means_unlimited() {
+    let candidates: Vec<Package> = (0..25)
+        .map(|i| package(&format!("q{i:02}"), "aur"))
+        .collect();
+    let unlimited = SearchOptions {
+        max_results: 0,
+        ..Default::default()
+    };
+    assert_eq!(ranked(&candidates, "zz", &unlimited).len(), 25);
+    let limited = SearchOptions {
+        max_results: 10,
+        ..Default::default()
+    };
+    assert_eq!(ranked(&candidates, "zz", &limited).len(), 10);
+}
+
+#[test]
+fn trusted_maintainers_get_the_boost() {
+    let maintained = |name: &str, maintainer: &str| Package {
+        maintainer: Some(maintainer.to_string()),
+        ..package(name, "aur")
+    };
+    let trusted = ["Alice".to_string()];
+    assert!(is_trusted(&maintained("vim-git", "alice"), &trusted));
+    assert!(!is_trusted(&maintained("vim-git", "bob"), &trusted));
+    assert!(!is_trusted(&package("vim", "official"), &trusted));
+
+    // `vim-gtk` is one edit further from the query, but its maintainer is
+    // trusted.
+    let mut candidates = [maintained("vim-gt", "bob"), maintained("vim-gtk", "alice")];
+    for pkg in &mut candidates {
+        pkg.trusted = is_trusted(pkg, &trusted);
+    }
+    let options = SearchOptions::default();
+    let breakdown = score_breakdown(&candidates[1], "vim", &["vim"], &options);
+    assert_eq!(breakdown.trusted_bonus, f64::from(TRUSTED_BOOST));
+    assert_eq!(ranked(&candidates, "vim", &options)[0], "vim-gtk/aur");
+}
+
+#[test]
+fn write_atomic_replaces_the_file_and_leaves_no_temp() {
+    let dir = env::temp_dir().join(format!("archlink-write-atomic-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("favorites.json");
+    fs::write(&path, "old").unwrap();
+    write_atomic(&path, "new").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    // A rename that fails leaves the old file as it was and cleans up the
+    // temporary file.
+    let failed = write_atomic_with(&path, b"newer", |_, _| Err(io::Error::other("no rename")));
+    assert!(failed.is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    // So does a real one, here onto a non-empty directory.
+    let blocked = dir.join("blocked");
+    fs::create_dir_all(blocked.join("inside")).unwrap();
+    assert!(write_atomic(&blocked, "new").is_err());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn score_breakdown_combines_the_weights_linearly() {
+    let pkg = Package {
+        description: "Vim, the text editor".to_string(),
+        votes: Some(99),
+        ..package("vim-git", "aur")
+    };
+    let query_words = ["vim", "editor"];
+    let options = SearchOptions {
+        ranking: RankingWeights {
+            name_distance: 2.0,
+            desc_match: 10.0,
+            source_official: 30.0,
+            aur_votes: 4.0,
+            recency: 0.0,
+        },
+        ..Default::default()
+    };
+    let breakdown = score_breakdown(&pkg, "vim editor", &query_words, &options);
+    let distance = levenshtein("vim-git", "vim editor") as u32;
+    assert_eq!(breakdown.name_distance, distance);
+    assert_eq!(breakdown.description_bonus, 20.0);
+    assert_eq!(breakdown.source_bonus, 0.0);
+    assert_eq!(breakdown.votes_bonus, 8.0);
+    let expected = 1000.0 - 2.0 * f64::from(distance) + 20.0 + 8.0;
+    assert_eq!(breakdown.score, expected as u32);
+
+    // Zero weights turn every signal off.
+    let options = SearchOptions {
+        ranking: RankingWeights {
+            name_distance: 0.0,
+            desc_match: 0.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let breakdown = score_breakdown(&pkg, "vim editor", &query_words, &options);
+    assert_eq!(breakdown.score, 1000);
+}
+
+#[test]
+fn read_manifest_skips_headers_and_comments() {
+    let manifest = "# archlink manifest, generated 2026-01-01\n\n[official]\nvim # 9.1-1\n  git\n\n[aur]\nvim-git # 9.1.r1-1\nvim\n";
+    assert_eq!(read_manifest(manifest), ["vim", "git", "vim-git"]);
+    assert!(read_manifest("# nothing\n[official]\n[aur]\n").is_empty());
+}
+
+#[test]
+fn aur_arch_mismatch_is_refused_unless_ignored() {
+    let info = |source, arch: &[&str]| PackageInfo {
+        name: "tool".to_string(),
+        version: "1.0-1".to_string(),
+        source,
+        installed_size: None,
+        depends: Vec::new(),
+        optdepends: Vec::new(),
+        votes: None,
+        arch: arch.iter().map(|a| a.to_string()).collect(),
+    };
+    let options = InstallOptions::default();
+    let error = check_aur_arch(&info("aur", &["nosucharch"]), &options).unwrap_err();
+    assert!(
+        strip_ansi(&error).contains("built for nosucharch"),
+        "{error}"
+    );
+    assert!(check_aur_arch(&info("aur", &["nosucharch", host_arch()]), &options).is_ok());
+    assert!(check_aur_arch(&info("aur", &["any"]), &options).is_ok());
+    assert!(check_aur_arch(&info("aur", &[]), &options).is_ok());
+    assert!(check_aur_arch(&info("official", &["nosucharch"]), &options).is_ok());
+
+    let options = InstallOptions {
+        ignore_arch: true,
+        ..Default::default()
+    };
+    assert!(check_aur_arch(&info("aur", &["nosucharch"]), &options).is_ok());
+}
+
+#[test]
+fn unknown_install_order_entries_are_dropped() {
+    let config: Config =
+        toml::from_str("install_order = [\"paru\", \"bogus\", \"pacman\"]\nmax_results = 3")
+            .unwrap();
+    assert_eq!(
+        config.install_order,
+        Some(vec![InstallTool::Paru, InstallTool::Pacman])
+    );
+    assert_eq!(config.max_results, Some(3));
+    assert_eq!(toml::from_str::<Config>("").unwrap().install_order, None);
+}
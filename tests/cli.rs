@@ -0,0 +1,247 @@
+//! Runs the built binary against the canned responses in `fixtures/`, with
+//! a scratch `HOME` and a `PATH` that holds only what each test puts there.
+
+use std::env;
+use std::fs::{self, File};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+
+/// A per-test directory for `HOME`, the XDG dirs and `PATH`, removed on drop.
+struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("archlink-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        Scratch { dir }
+    }
+
+    /// Puts a `pacman` on `PATH` that answers `-Qq` with `installed`.
+    fn fake_pacman(&self, installed: &[&str]) {
+        let script = format!(
+            "[ \"$1\" = -Qq ] && printf '%s\\n' {}\nexit 0\n",
+            installed.join(" ")
+        );
+        self.fake_command("pacman", &script);
+    }
+
+    /// Puts an executable `name` on `PATH` that runs the shell `script`.
+    fn fake_command(&self, name: &str, script: &str) {
+        let path = self.dir.join("bin").join(name);
+        fs::write(&path, format!("#!/bin/sh\n{script}")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// Runs archlink with the whitespace-separated `args` and `stdin` as
+    /// input and returns its exit status and its stdout and stderr
+    /// interleaved, like `2>&1` does.
+    fn run(&self, args: &str, stdin: Stdio) -> (ExitStatus, String) {
+        self.run_with_env(args, &[], stdin)
+    }
+
+    /// Like `run`, with `vars` added to the environment.
+    fn run_with_env(
+        &self,
+        args: &str,
+        vars: &[(&str, &str)],
+        stdin: Stdio,
+    ) -> (ExitStatus, String) {
+        let log = self.dir.join("output.txt");
+        let out = File::create(&log).unwrap();
+        let status = Command::new(env!("CARGO_BIN_EXE_archlink"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args(args.split_whitespace())
+            .env_clear()
+            .env("PATH", self.dir.join("bin"))
+            .env("HOME", &self.dir)
+            .env("XDG_CACHE_HOME", self.dir.join("cache"))
+            .env("XDG_STATE_HOME", self.dir.join("state"))
+            .envs(vars.iter().copied())
+            .stdin(stdin)
+            .stdout(out.try_clone().unwrap())
+            .stderr(out)
+            .status()
+            .unwrap();
+        (status, fs::read_to_string(&log).unwrap())
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn only_installed_without_pacman_fails_early() {
+    let scratch = Scratch::new("no-pacman");
+    let args = "--no-color search vim --only-installed --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert_eq!(status.code(), Some(1));
+    assert!(output.contains("pacman not found in PATH"), "{output}");
+}
+
+#[test]
+fn prompt_at_end_of_input_exits_cleanly() {
+    let scratch = Scratch::new("prompt-eof");
+    let args = "--no-color search vim --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert!(status.success(), "{output}");
+    assert!(output.ends_with("(0 to exit): "), "{output}");
+}
+
+#[test]
+fn prompt_with_invalid_utf8_exits_cleanly() {
+    let scratch = Scratch::new("prompt-utf8");
+    let input = scratch.dir.join("input");
+    fs::write(&input, b"\xff\xfe1\n").unwrap();
+    let args = "--no-color search vim --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, File::open(&input).unwrap().into());
+    assert!(status.success(), "{output}");
+    assert!(output.ends_with("(0 to exit): "), "{output}");
+}
+
+/// Each file in `fixtures/expected/`, the `search` arguments that produce it
+/// and the packages the fake pacman reports as installed.
+const EXPECTED: &[(&str, &str, &[&str])] = &[
+    ("search-vim", "vim", &[]),
+    ("search-vim-limit-1", "vim --limit 1", &[]),
+    ("search-empty", "nosuchpackage", &[]),
+    // `gvim` has no AUR fixture, so the AUR search fails.
+    ("search-partial", "gvim", &[]),
+    ("search-prefix", "vim --prefix", &[]),
+    ("search-installed", "vim --only-installed", &["vim"]),
+];
+
+/// Compares each search's output with its file in `fixtures/expected/`.
+/// With `ARCHLINK_UPDATE_EXPECTED=1`, rewrites the files instead.
+#[test]
+fn search_output_matches_expected() {
+    let expected_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/expected");
+    let update = env::var_os("ARCHLINK_UPDATE_EXPECTED").is_some_and(|v| v == "1");
+    for (name, query, installed) in EXPECTED {
+        let scratch = Scratch::new(name);
+        scratch.fake_pacman(installed);
+        let args = format!("--no-color search {query} --test-fixtures fixtures");
+        let (_, output) = scratch.run(&args, Stdio::null());
+        let path = expected_dir.join(format!("{name}.txt"));
+        if update {
+            fs::write(&path, &output).unwrap();
+        } else {
+            assert_eq!(output, fs::read_to_string(&path).unwrap(), "{name}");
+        }
+    }
+
+    for entry in fs::read_dir(&expected_dir).unwrap() {
+        let file = entry.unwrap().file_name().into_string().unwrap();
+        let name = file.trim_end_matches(".txt");
+        let known = EXPECTED.iter().any(|(expected, ..)| *expected == name);
+        assert!(known, "no case for {file}");
+    }
+}
+
+#[test]
+fn csv_rows_end_in_crlf() {
+    let scratch = Scratch::new("csv");
+    let args = "--no-color search vim --output csv --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert!(status.success(), "{output}");
+    let rows: Vec<&str> = output.split_inclusive('\n').collect();
+    assert_eq!(rows.len(), 4, "{output}");
+    assert!(rows.iter().all(|row| row.ends_with("\r\n")), "{output:?}");
+}
+
+#[test]
+fn json_array_works_with_output_json() {
+    let scratch = Scratch::new("json-array");
+    let args = "--no-color search vim --output json --json-array --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert!(status.success(), "{output}");
+    assert!(output.starts_with("[{\"name\":\"vim\""), "{output}");
+
+    let args = "--no-color search vim --output csv --json-array --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert_eq!(status.code(), Some(1), "{output}");
+}
+
+#[test]
+fn invalid_max_results_env_warns_once() {
+    let scratch = Scratch::new("max-results-env");
+    let vars = [("ARCHLINK_MAX_RESULTS", "abc")];
+    let (status, output) = scratch.run_with_env("--no-color config show", &vars, Stdio::null());
+    assert!(status.success(), "{output}");
+    assert_eq!(
+        output.matches("ARCHLINK_MAX_RESULTS 'abc'").count(),
+        1,
+        "{output}"
+    );
+
+    let vars = [("ARCHLINK_MAX_RESULTS", "7")];
+    let (_, output) = scratch.run_with_env("--no-color config show", &vars, Stdio::null());
+    assert!(
+        output.contains("max_results = 7 # from ARCHLINK_MAX_RESULTS"),
+        "{output}"
+    );
+}
+
+#[test]
+fn exported_manifest_installs_from_file() {
+    let scratch = Scratch::new("manifest");
+    let calls = scratch.dir.join("calls.txt");
+    let calls = calls.display();
+    // `vim` and `gvim` are from the repos and `vim-git` from the AUR; none
+    // counts as installed when `install` checks with `-Q`.
+    scratch.fake_command(
+        "pacman",
+        &format!(
+            "case \"$1\" in\n\
+             -Qe) printf 'vim 9.1.0866-1\\ngvim 9.1.0866-1\\nvim-git 9.1.0900-1\\n' ;;\n\
+             -Qem) printf 'vim-git 9.1.0900-1\\n' ;;\n\
+             -Q*) exit 1 ;;\n\
+             *) echo pacman \"$@\" >> {calls} ;;\n\
+             esac\n"
+        ),
+    );
+    scratch.fake_command("sudo", "exec \"$@\"\n");
+    scratch.fake_command("yay", &format!("echo yay \"$@\" >> {calls}\n"));
+
+    let manifest = scratch.dir.join("packages.txt");
+    let args = format!("--no-color export-manifest {}", manifest.display());
+    let (status, output) = scratch.run(&args, Stdio::null());
+    assert!(status.success(), "{output}");
+
+    let args = format!(
+        "--no-color install --from-file {} --test-fixtures fixtures",
+        manifest.display()
+    );
+    let (status, output) = scratch.run(&args, Stdio::null());
+    assert!(status.success(), "{output}");
+    assert_eq!(
+        output.matches("Successfully installed").count(),
+        3,
+        "{output}"
+    );
+    let calls = fs::read_to_string(scratch.dir.join("calls.txt")).unwrap();
+    assert_eq!(
+        calls,
+        "pacman -S vim gvim --noconfirm --needed\nyay -S --needed vim-git\n"
+    );
+}
+
+#[test]
+fn fail_fast_exits_non_zero_when_a_source_failed() {
+    let scratch = Scratch::new("fail-fast");
+    let args = "--no-color search gvim --fail-fast --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert_eq!(status.code(), Some(1), "{output}");
+    assert!(output.contains("gvim"), "{output}");
+    assert!(!output.ends_with("(0 to exit): "), "{output}");
+
+    let args = "--no-color search vim --fail-fast --test-fixtures fixtures";
+    let (status, output) = scratch.run(args, Stdio::null());
+    assert!(status.success(), "{output}");
+}